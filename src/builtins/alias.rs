@@ -0,0 +1,227 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+use toml_edit::Array;
+
+use crate::builtins::{parse_document, save_config, split_flags, take_save};
+use crate::command::Builtin;
+use crate::eval::CommandError;
+use crate::lexer::quote_word;
+use crate::shell::Shell;
+
+// Sets, or with `None` removes, an alias in `[misc].alias` of a config file's text
+// Everything else in the file is kept as it was written, comments included
+pub fn update_alias(config: &str, name: &str, value: Option<&str>) -> Result<String, String> {
+    let mut document = parse_document(config)?;
+    let misc = document
+        .entry("misc")
+        .or_insert(toml_edit::table())
+        .as_table_like_mut()
+        .ok_or("`misc` is not a table")?;
+    let aliases = misc
+        .entry("alias")
+        .or_insert(toml_edit::value(Array::new()))
+        .as_array_mut()
+        .ok_or("`misc.alias` is not an array")?;
+
+    // The default config comes with an empty `["", ""]` entry, it goes as well
+    aliases.retain(|entry| {
+        let key = entry
+            .as_array()
+            .and_then(|entry| entry.get(0))
+            .and_then(|key| key.as_str());
+        key != Some(name) && key != Some("")
+    });
+    if let Some(value) = value {
+        aliases.push(Array::from_iter([name, value]));
+    }
+    Ok(document.to_string())
+}
+
+fn print_alias(name: &str, value: &str) {
+    println!("alias {}={}", name, quote_word(value));
+}
+
+pub struct Alias;
+
+impl Builtin for Alias {
+    fn name() -> &'static str {
+        "alias"
+    }
+
+    fn about() -> &'static str {
+        "Define or show aliases, --save also writes them to the config file"
+    }
+
+    fn examples() -> [&'static str; 3] {
+        ["alias", "alias ll='ls -l'", "alias --save gs='git status'"]
+    }
+
+    fn run(shell: &mut Shell, args: Vec<String>) -> Result<(), CommandError> {
+        let (save, args) = take_save(args);
+        let (_, args) =
+            split_flags(args, "p").map_err(|e| CommandError::Error(format!("alias: {}", e)))?;
+
+        if args.is_empty() {
+            let mut aliases = shell.aliases.iter().collect::<Vec<_>>();
+            aliases.sort();
+            for (name, value) in aliases {
+                print_alias(name, value);
+            }
+            return Ok(());
+        }
+
+        let mut status = 0;
+        for arg in args {
+            match arg.split_once('=') {
+                Some((name, _)) if name.is_empty() || name.contains('/') => {
+                    eprintln!("vsh: alias: `{}`: invalid alias name", name);
+                    status = 1;
+                }
+                Some((name, value)) => {
+                    if save {
+                        save_config(&shell.config_path, |config| {
+                            update_alias(config, name, Some(value))
+                        })
+                        .map_err(|e| CommandError::Error(format!("alias: {}", e)))?;
+                    }
+                    shell.aliases.insert(name.to_string(), value.to_string());
+                }
+                None => match shell.aliases.get(&arg) {
+                    Some(value) => print_alias(&arg, value),
+                    None => {
+                        eprintln!("vsh: alias: {}: not found", arg);
+                        status = 1;
+                    }
+                },
+            }
+        }
+
+        if status == 0 {
+            Ok(())
+        } else {
+            Err(CommandError::Finished(status))
+        }
+    }
+}
+
+pub struct Unalias;
+
+impl Builtin for Unalias {
+    fn name() -> &'static str {
+        "unalias"
+    }
+
+    fn about() -> &'static str {
+        "Remove aliases, or all of them with -a; --save also removes them from the config file"
+    }
+
+    fn examples() -> [&'static str; 3] {
+        ["unalias ll", "unalias -a", "unalias --save gs"]
+    }
+
+    fn run(shell: &mut Shell, args: Vec<String>) -> Result<(), CommandError> {
+        let (save, args) = take_save(args);
+        let (flags, mut names) =
+            split_flags(args, "a").map_err(|e| CommandError::Error(format!("unalias: {}", e)))?;
+
+        if flags.contains(&'a') {
+            names = shell.aliases.keys().cloned().collect();
+            names.sort();
+        } else if names.is_empty() {
+            return Err(CommandError::Error(
+                "unalias: usage: unalias [-a] [--save] name [name ...]".to_string(),
+            ));
+        }
+
+        let mut status = 0;
+        for name in names {
+            if shell.aliases.remove(&name).is_none() {
+                eprintln!("vsh: unalias: {}: not found", name);
+                status = 1;
+                continue;
+            }
+            if save {
+                save_config(&shell.config_path, |config| {
+                    update_alias(config, &name, None)
+                })
+                .map_err(|e| CommandError::Error(format!("unalias: {}", e)))?;
+            }
+        }
+
+        if status == 0 {
+            Ok(())
+        } else {
+            Err(CommandError::Finished(status))
+        }
+    }
+}
+
+// Fish style abbreviations, the line editor expands them in place so history shows the full command
+pub struct Abbr;
+
+impl Builtin for Abbr {
+    fn name() -> &'static str {
+        "abbr"
+    }
+
+    fn about() -> &'static str {
+        "Add abbreviations with -a, erase them with -e, list their names with -l or show them all"
+    }
+
+    fn examples() -> [&'static str; 3] {
+        ["abbr", "abbr -a gco git checkout", "abbr -e gco"]
+    }
+
+    fn run(shell: &mut Shell, args: Vec<String>) -> Result<(), CommandError> {
+        let (flags, args) =
+            split_flags(args, "aels").map_err(|e| CommandError::Error(format!("abbr: {}", e)))?;
+
+        match (flags.as_slice(), args.as_slice()) {
+            ([] | ['s'], []) => {
+                for (name, expansion) in shell.abbreviations.list() {
+                    println!("abbr -a {} {}", name, quote_word(&expansion));
+                }
+                Ok(())
+            }
+            (['l'], []) => {
+                for (name, _) in shell.abbreviations.list() {
+                    println!("{}", name);
+                }
+                Ok(())
+            }
+            (['a'], [name, expansion @ ..]) if !expansion.is_empty() => {
+                if name.contains(char::is_whitespace) {
+                    return Err(CommandError::Error(format!(
+                        "abbr: `{}`: invalid abbreviation name",
+                        name
+                    )));
+                }
+                shell
+                    .abbreviations
+                    .insert(name.to_string(), expansion.join(" "));
+                Ok(())
+            }
+            (['e'], names) if !names.is_empty() => {
+                let mut status = 0;
+                for name in names {
+                    if shell.abbreviations.remove(name).is_none() {
+                        eprintln!("vsh: abbr: {}: not found", name);
+                        status = 1;
+                    }
+                }
+                if status == 0 {
+                    Ok(())
+                } else {
+                    Err(CommandError::Finished(status))
+                }
+            }
+            _ => Err(CommandError::Error(
+                "abbr: usage: abbr [-s | -l | -a name expansion... | -e name...]".to_string(),
+            )),
+        }
+    }
+}
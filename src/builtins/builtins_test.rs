@@ -0,0 +1,167 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::builtins::alias::update_alias;
+    use crate::builtins::config::{parse_value, set_value, update_config};
+    use crate::builtins::introspect::function_text;
+    use crate::builtins::resource::{parse_umask, symbolic_umask};
+    use crate::builtins::*;
+    use crate::shell::{Resolution, Shell, MAX_DEPTH};
+
+    fn strings(v: &[&str]) -> Vec<String> {
+        v.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn split_flags_test() {
+        let (flags, rest) = split_flags(strings(&["-at", "ls", "-p"]), "atp").unwrap();
+        assert_eq!(flags, vec!['a', 't']);
+        assert_eq!(rest, strings(&["ls", "-p"]));
+
+        let (flags, rest) = split_flags(strings(&["--", "-a"]), "a").unwrap();
+        assert!(flags.is_empty());
+        assert_eq!(rest, strings(&["-a"]));
+
+        assert!(split_flags(strings(&["-x"]), "a").is_err());
+    }
+
+    #[test]
+    fn resolve_order_test() {
+        let mut aliases = HashMap::new();
+        aliases.insert("cd".to_string(), "echo cd".to_string());
+        let shell = Shell::new(aliases);
+
+        let found = shell.resolve("cd");
+        assert_eq!(found[0], Resolution::Alias("echo cd".to_string()));
+        assert_eq!(found[1], Resolution::Builtin);
+        assert!(shell.resolve("surely-not-a-command").is_empty());
+    }
+
+    #[test]
+    fn umask_test() {
+        assert_eq!(parse_umask("077", 0o022), Ok(0o077));
+        assert!(parse_umask("1777", 0o022).is_err());
+        assert_eq!(parse_umask("u=rwx,g=rx,o=", 0o022), Ok(0o027));
+        assert_eq!(parse_umask("g-w", 0o002), Ok(0o022));
+        assert_eq!(parse_umask("a+w", 0o022), Ok(0o000));
+        assert_eq!(parse_umask("o=r", 0o027), Ok(0o023));
+        assert!(parse_umask("u=rwq", 0o022).is_err());
+
+        assert_eq!(symbolic_umask(0o022), "u=rwx,g=rx,o=rx");
+        assert_eq!(symbolic_umask(0o077), "u=rwx,g=,o=");
+    }
+
+    #[test]
+    fn update_alias_test() {
+        let config =
+            "[prompt]\ntheme = \"$ \"\n\n[misc]\nalias = [[\"\", \"\"], [\"ll\", \"ls -l\"]]\n";
+        let updated = update_alias(config, "gs", Some("git status")).unwrap();
+        let value = toml::from_str::<toml::Value>(&updated).unwrap();
+        assert_eq!(value["prompt"]["theme"].as_str(), Some("$ "));
+        assert_eq!(
+            value["misc"]["alias"],
+            toml::Value::try_from(vec![["ll", "ls -l"], ["gs", "git status"]]).unwrap()
+        );
+
+        let updated = update_alias(&updated, "ll", None).unwrap();
+        let value = toml::from_str::<toml::Value>(&updated).unwrap();
+        assert_eq!(
+            value["misc"]["alias"],
+            toml::Value::try_from(vec![["gs", "git status"]]).unwrap()
+        );
+
+        let value =
+            toml::from_str::<toml::Value>(&update_alias("", "a", Some("b")).unwrap()).unwrap();
+        assert_eq!(value["misc"]["alias"][0][1].as_str(), Some("b"));
+        assert!(update_alias("misc = 1", "a", Some("b")).is_err());
+
+        // The rest of a hand written file is left as it was
+        let config = "# mine\n[prompt]\ntheme = \"$ \" # short\n\n[misc]\nalias = []\n";
+        assert_eq!(
+            update_alias(config, "gs", Some("git status")).unwrap(),
+            "# mine\n[prompt]\ntheme = \"$ \" # short\n\n[misc]\nalias = [[\"gs\", \"git status\"]]\n"
+        );
+    }
+
+    #[test]
+    fn config_set_test() {
+        assert_eq!(parse_value("true"), toml::Value::Boolean(true));
+        assert_eq!(
+            parse_value("[1, 2]"),
+            toml::Value::try_from([1, 2]).unwrap()
+        );
+        assert_eq!(parse_value("`$ `"), toml::Value::String("`$ `".to_string()));
+
+        let table = set_value(Default::default(), "effects.bold", parse_value("false")).unwrap();
+        assert_eq!(table["effects"]["bold"].as_bool(), Some(false));
+        let e = set_value(table.clone(), "effects.bld", parse_value("1")).unwrap_err();
+        assert!(e.contains("did you mean `bold`?"));
+        assert!(set_value(table.clone(), "effects.bold", parse_value("yes")).is_err());
+        assert!(set_value(table.clone(), "prompt.theme", parse_value("&[")).is_err());
+        assert!(set_value(table, "bold", parse_value("true")).is_err());
+
+        let config = "[prompt]\ntheme = \"$ \"\n\n[effects]\nbold = true\n";
+        let updated = update_config(config, "effects.bold", parse_value("false")).unwrap();
+        let value = toml::from_str::<toml::Value>(&updated).unwrap();
+        assert_eq!(value["prompt"]["theme"].as_str(), Some("$ "));
+        assert_eq!(value["effects"]["bold"].as_bool(), Some(false));
+
+        // `include` is a plain value and has to stay ahead of every table
+        let config = "include = [\"team.toml\"]\n\n[prompt]\ntheme = \"$ \"\n";
+        let updated = update_config(config, "env.EDITOR", parse_value("vim")).unwrap();
+        let value = toml::from_str::<toml::Value>(&updated).unwrap();
+        assert_eq!(value["include"][0].as_str(), Some("team.toml"));
+        assert_eq!(value["env"]["EDITOR"].as_str(), Some("vim"));
+
+        // Comments, order and the comment after a changed value stay
+        let config = "[effects]\n# keep\nbold = true # why\n\n[prompt]\ntheme = \"$ \"\n";
+        assert_eq!(
+            update_config(config, "effects.bold", parse_value("false")).unwrap(),
+            "[effects]\n# keep\nbold = false # why\n\n[prompt]\ntheme = \"$ \"\n"
+        );
+    }
+
+    #[test]
+    fn source_nesting_test() {
+        let path = std::env::temp_dir().join(format!("vsh-source-{}.vsh", std::process::id()));
+        std::fs::write(&path, format!("source {}\n", path.display())).unwrap();
+        let file = path.display().to_string();
+        // Debug builds need more stack than a test thread has to reach the limit
+        let result = std::thread::Builder::new()
+            .stack_size(256 << 20)
+            .spawn(move || {
+                let mut shell = Shell::new(HashMap::new());
+                let result = source::Source::run(&mut shell, vec![file]);
+                (result, shell.depth)
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+        assert!(matches!(result, (Err(CommandError::Finished(1)), 0)));
+
+        let mut shell = Shell::new(HashMap::new());
+        shell.depth = MAX_DEPTH;
+        let result = source::Source::run(&mut shell, vec![path.display().to_string()]);
+        std::fs::remove_file(&path).ok();
+        assert!(matches!(
+            result,
+            Err(CommandError::Error(e)) if e == "source: maximum nesting level exceeded"
+        ));
+    }
+
+    #[test]
+    fn function_text_test() {
+        assert_eq!(function_text("f", "echo multi"), "f() { echo multi; }");
+        assert_eq!(function_text("f", "echo a;"), "f() { echo a; }");
+        assert_eq!(
+            function_text("f", "  echo one\n  echo two"),
+            "f() {\n  echo one\n  echo two\n}"
+        );
+    }
+}
@@ -10,7 +10,8 @@ use std::path::Path;
 use crate::eval::CommandError;
 
 use crate::command::Builtin;
-use crate::utils::expand;
+use crate::shell::Shell;
+use crate::utils::{expand, home_dir};
 
 pub struct Cd;
 
@@ -27,8 +28,8 @@ impl Builtin for Cd {
         ["cd", "cd ~/Downloads", "cd .."]
     }
 
-    fn run(args: Vec<String>) -> Result<(), CommandError> {
-        match args.get(0) {
+    fn run(_shell: &mut Shell, args: Vec<String>) -> Result<(), CommandError> {
+        match args.first() {
             Some(dir) => {
                 if env::set_current_dir(Path::new(&expand(dir.to_string()))).is_err() {
                     Err(CommandError::Error("No such directory".to_string()))
@@ -37,7 +38,9 @@ impl Builtin for Cd {
                 }
             }
             None => {
-                if env::set_current_dir(env::var("HOME").unwrap()).is_err() {
+                let home = home_dir()
+                    .ok_or_else(|| CommandError::Error("cd: HOME not set".to_string()))?;
+                if env::set_current_dir(home).is_err() {
                     Err(CommandError::Error(
                         "Could not enter HOME directory".to_string(),
                     ))
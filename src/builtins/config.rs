@@ -0,0 +1,263 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+use std::env;
+
+use toml::value::Table;
+use toml::Value;
+use toml_edit::Item;
+
+use crate::builtins::{edit_value, parse_document, save_config, take_save};
+use crate::command::Builtin;
+use crate::eval::{CommandError, Vshcommand};
+use crate::lexer::quote_word;
+use crate::shell::Shell;
+use crate::utils::{validate_config, validate_theme, Config};
+
+// Splits `section.key` into its parts
+fn split_key(key: &str) -> Result<(&str, &str), String> {
+    match key.split_once('.') {
+        Some((section, name)) if !section.is_empty() && !name.is_empty() => Ok((section, name)),
+        _ => Err(format!("{}: a setting is written `section.key`", key)),
+    }
+}
+
+// The value written after `config set key`, TOML like `true` or `[1, 2]`, and otherwise a
+// plain string
+pub fn parse_value(text: &str) -> Value {
+    match toml::from_str::<Table>(&format!("value = {}", text)) {
+        Ok(mut table) => table.remove("value").unwrap(),
+        Err(_) => Value::String(text.to_string()),
+    }
+}
+
+// `table` with `section.key` set to `value`, checked against what the config takes
+pub fn set_value(mut table: Table, key: &str, value: Value) -> Result<Table, String> {
+    let (section, name) = split_key(key)?;
+    let mut setting = Table::new();
+    setting.insert(name.to_string(), value.clone());
+    let mut snippet = Table::new();
+    snippet.insert(section.to_string(), Value::Table(setting));
+    let text = toml::to_string(&snippet).map_err(|e| e.to_string())?;
+    if let Some(problem) = validate_config(&text).into_iter().next() {
+        return Err(problem.message);
+    }
+    if key == "prompt.theme" {
+        validate_theme(value.as_str().unwrap_or_default()).map_err(|e| format!("theme: {}", e))?;
+    }
+
+    table
+        .entry(section)
+        .or_insert_with(|| Value::Table(Table::new()))
+        .as_table_mut()
+        .ok_or_else(|| format!("`{}` is not a table", section))?
+        .insert(name.to_string(), value);
+    Ok(table)
+}
+
+// Sets `key` in a config file's text, everything else is kept as it was written
+pub fn update_config(config: &str, key: &str, value: Value) -> Result<String, String> {
+    let table = toml::from_str::<Table>(config).map_err(|e| e.to_string())?;
+    set_value(table, key, value.clone())?;
+
+    let (section, name) = split_key(key)?;
+    let mut document = parse_document(config)?;
+    let entries = document
+        .entry(section)
+        .or_insert(toml_edit::table())
+        .as_table_like_mut()
+        .ok_or_else(|| format!("`{}` is not a table", section))?;
+    // A value already there is replaced where it is, keeping the comments around it
+    let mut value = edit_value(&value);
+    match entries.get_mut(name) {
+        Some(Item::Value(old)) => {
+            *value.decor_mut() = old.decor().clone();
+            *old = value;
+        }
+        _ => {
+            entries.insert(name, Item::Value(value));
+        }
+    }
+    Ok(document.to_string())
+}
+
+// Strings are printed as they are, so `$(config get prompt.theme)` gives the theme itself
+fn show(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        value => value.to_string(),
+    }
+}
+
+// Every setting in use as `section.key = value`
+fn settings(config: &Config) -> Vec<(String, Value)> {
+    let table = match Value::try_from(config) {
+        Ok(Value::Table(table)) => table,
+        _ => return Vec::new(),
+    };
+    let mut found = Vec::new();
+    for (section, entries) in table {
+        for (name, value) in entries.as_table().into_iter().flatten() {
+            found.push((format!("{}.{}", section, name), value.clone()));
+        }
+    }
+    found
+}
+
+pub struct ConfigBuiltin;
+
+impl ConfigBuiltin {
+    fn get(shell: &Shell, args: &[String]) -> Result<(), CommandError> {
+        let key = match args {
+            [key] => key,
+            _ => {
+                return Err(CommandError::Error(
+                    "config: usage: config get section.key".to_string(),
+                ))
+            }
+        };
+        let found = settings(&shell.config)
+            .into_iter()
+            .filter(|(name, _)| name == key || name.starts_with(&format!("{}.", key)))
+            .collect::<Vec<_>>();
+        match found.as_slice() {
+            [] => {
+                eprintln!("vsh: config: {}: not set", key);
+                Err(CommandError::Finished(1))
+            }
+            [(name, value)] if name == key => {
+                println!("{}", show(value));
+                Ok(())
+            }
+            // A whole section
+            found => {
+                for (name, value) in found {
+                    println!("{} = {}", name, value);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn set(shell: &mut Shell, args: Vec<String>) -> Result<(), CommandError> {
+        let (save, args) = take_save(args);
+        let (key, value) = match args.as_slice() {
+            [key, value] => (key, parse_value(value)),
+            _ => {
+                return Err(CommandError::Error(
+                    "config: usage: config set [--save] section.key value".to_string(),
+                ))
+            }
+        };
+
+        let error = |e: String| CommandError::Error(format!("config: {}", e));
+        let table = match Value::try_from(&shell.config) {
+            Ok(Value::Table(table)) => table,
+            _ => Table::new(),
+        };
+        let table = set_value(table, key, value.clone()).map_err(error)?;
+        let config = Value::Table(table)
+            .try_into::<Config>()
+            .map_err(|e| error(format!("{}: {}", key, e)))?;
+        shell.apply_config(config);
+
+        if save {
+            save_config(&shell.config_path, |config| {
+                update_config(config, key, value)
+            })
+            .map_err(error)?;
+        }
+        Ok(())
+    }
+
+    // Opens the config file in $VISUAL or $EDITOR and reads it again after
+    fn edit(shell: &mut Shell) -> Result<(), CommandError> {
+        let editor = env::var("VISUAL")
+            .or_else(|_| env::var("EDITOR"))
+            .ok()
+            .filter(|editor| !editor.is_empty())
+            .unwrap_or_else(|| "vi".to_string());
+        let path = shell.config_path.to_string_lossy().to_string();
+        Vshcommand::run(format!("{} {}", editor, quote_word(&path)), shell)?;
+
+        if shell.reload_config() {
+            Ok(())
+        } else {
+            eprintln!("vsh: config: keeping the previous config");
+            Err(CommandError::Finished(1))
+        }
+    }
+}
+
+impl Builtin for ConfigBuiltin {
+    fn name() -> &'static str {
+        "config"
+    }
+
+    fn about() -> &'static str {
+        "Show or change settings of the config, `set --save` also writes them to config.toml"
+    }
+
+    fn examples() -> [&'static str; 3] {
+        [
+            "config get effects.bold",
+            "config set --save prompt.theme '`$ `'",
+            "config edit",
+        ]
+    }
+
+    fn run(shell: &mut Shell, mut args: Vec<String>) -> Result<(), CommandError> {
+        if args.is_empty() {
+            return Err(CommandError::Error(
+                "config: usage: config get|set|list|edit ...".to_string(),
+            ));
+        }
+        match args.remove(0).as_str() {
+            "get" => Self::get(shell, &args),
+            "set" => Self::set(shell, args),
+            "list" => {
+                for (name, value) in settings(&shell.config) {
+                    println!("{} = {}", name, value);
+                }
+                Ok(())
+            }
+            "edit" => Self::edit(shell),
+            command => Err(CommandError::Error(format!(
+                "config: {}: unknown command, use get, set, list or edit",
+                command
+            ))),
+        }
+    }
+}
+
+pub struct Reload;
+
+impl Builtin for Reload {
+    fn name() -> &'static str {
+        "reload"
+    }
+
+    fn about() -> &'static str {
+        "Read the config file again, vsh also does this before a prompt when the file changed"
+    }
+
+    fn examples() -> [&'static str; 3] {
+        [
+            "reload",
+            "vim ~/.config/vsh/config.toml; reload",
+            "reload && echo reloaded",
+        ]
+    }
+
+    fn run(shell: &mut Shell, _args: Vec<String>) -> Result<(), CommandError> {
+        if shell.reload_config() {
+            Ok(())
+        } else {
+            eprintln!("vsh: reload: keeping the previous config");
+            Err(CommandError::Finished(1))
+        }
+    }
+}
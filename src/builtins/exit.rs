@@ -0,0 +1,81 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+use crate::command::Builtin;
+use crate::eval::CommandError;
+use crate::shell::Shell;
+
+// Status given to `exit` or `return`, falls back to the status of the last command
+fn status_arg(name: &str, shell: &Shell, args: &[String]) -> Result<i32, CommandError> {
+    match args {
+        [] => Ok(shell.last_status),
+        [code] => match code.parse::<i64>() {
+            Ok(code) => Ok((code & 0xff) as i32),
+            Err(_) => Err(CommandError::Error(format!(
+                "{}: {}: numeric argument required",
+                name, code
+            ))),
+        },
+        _ => Err(CommandError::Error(format!("{}: too many arguments", name))),
+    }
+}
+
+pub struct Exit;
+
+impl Builtin for Exit {
+    fn name() -> &'static str {
+        "exit"
+    }
+
+    fn about() -> &'static str {
+        "Exit the shell with the given status, or the status of the last command"
+    }
+
+    fn examples() -> [&'static str; 3] {
+        ["exit", "exit 1", "exit $?"]
+    }
+
+    fn run(shell: &mut Shell, args: Vec<String>) -> Result<(), CommandError> {
+        match status_arg(Self::name(), shell, &args) {
+            Ok(code) => Err(CommandError::Exit(code)),
+            Err(CommandError::Error(e)) => {
+                // Like other shells, a bad status still exits
+                eprintln!("vsh: {}", e);
+                Err(CommandError::Exit(2))
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+pub struct Return;
+
+impl Builtin for Return {
+    fn name() -> &'static str {
+        "return"
+    }
+
+    fn about() -> &'static str {
+        "Leave the current function or sourced file with the given status"
+    }
+
+    fn examples() -> [&'static str; 3] {
+        ["return", "return 1", "return $?"]
+    }
+
+    fn run(shell: &mut Shell, args: Vec<String>) -> Result<(), CommandError> {
+        if shell.depth == 0 {
+            return Err(CommandError::Error(
+                "return: can only `return` from a function or sourced file".to_string(),
+            ));
+        }
+        Err(CommandError::Return(status_arg(
+            Self::name(),
+            shell,
+            &args,
+        )?))
+    }
+}
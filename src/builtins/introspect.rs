@@ -0,0 +1,231 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+use crate::builtins::{self, split_flags};
+use crate::command::Builtin;
+use crate::eval::{CommandError, Vshcommand};
+use crate::shell::{Resolution, Shell};
+
+// A function the way it can be read back in: a body of several lines keeps them, one line
+// gets the `;` that has to come before `}`
+pub fn function_text(name: &str, body: &str) -> String {
+    if body.contains('\n') {
+        format!("{}() {{\n{}\n}}", name, body)
+    } else if body.is_empty() || body.ends_with([';', '&']) {
+        format!("{}() {{ {} }}", name, body)
+    } else {
+        format!("{}() {{ {}; }}", name, body)
+    }
+}
+
+pub struct Type;
+
+impl Builtin for Type {
+    fn name() -> &'static str {
+        "type"
+    }
+
+    fn about() -> &'static str {
+        "Show how each name would be interpreted if used as a command"
+    }
+
+    fn examples() -> [&'static str; 3] {
+        ["type ls", "type -a ls", "type -t cd"]
+    }
+
+    fn run(shell: &mut Shell, args: Vec<String>) -> Result<(), CommandError> {
+        let (flags, names) =
+            split_flags(args, "atp").map_err(|e| CommandError::Error(format!("type: {}", e)))?;
+        let all = flags.contains(&'a');
+        let kind_only = flags.contains(&'t');
+        let path_only = flags.contains(&'p');
+
+        let mut missing = false;
+        for name in names {
+            let mut found = shell.resolve(&name);
+            if found.is_empty() {
+                if !kind_only && !path_only {
+                    eprintln!("vsh: type: {}: not found", name);
+                }
+                missing = true;
+                continue;
+            }
+            if !all {
+                found.truncate(1);
+            }
+
+            for resolution in found {
+                match (resolution, kind_only, path_only) {
+                    (Resolution::Alias(_), true, _) => println!("alias"),
+                    (Resolution::Function(_), true, _) => println!("function"),
+                    (Resolution::Builtin, true, _) => println!("builtin"),
+                    (Resolution::File(_), true, _) => println!("file"),
+                    (Resolution::File(path), _, true) => println!("{}", path.display()),
+                    (_, _, true) => (),
+                    (Resolution::Alias(value), ..) => {
+                        println!("{} is aliased to `{}`", name, value)
+                    }
+                    (Resolution::Function(body), ..) => {
+                        println!("{} is a function\n{}", name, function_text(&name, &body))
+                    }
+                    (Resolution::Builtin, ..) => println!("{} is a shell builtin", name),
+                    (Resolution::File(path), ..) => println!("{} is {}", name, path.display()),
+                }
+            }
+        }
+
+        if missing {
+            Err(CommandError::Finished(1))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+pub struct Which;
+
+impl Builtin for Which {
+    fn name() -> &'static str {
+        "which"
+    }
+
+    fn about() -> &'static str {
+        "Locate a command, including aliases and builtins"
+    }
+
+    fn examples() -> [&'static str; 3] {
+        ["which ls", "which -a python", "which ll cd"]
+    }
+
+    fn run(shell: &mut Shell, args: Vec<String>) -> Result<(), CommandError> {
+        let (flags, names) =
+            split_flags(args, "a").map_err(|e| CommandError::Error(format!("which: {}", e)))?;
+
+        let mut missing = false;
+        for name in names {
+            let mut found = shell.resolve(&name);
+            if found.is_empty() {
+                eprintln!("{} not found", name);
+                missing = true;
+                continue;
+            }
+            if !flags.contains(&'a') {
+                found.truncate(1);
+            }
+
+            for resolution in found {
+                match resolution {
+                    Resolution::Alias(value) => println!("{}: aliased to {}", name, value),
+                    Resolution::Function(body) => println!("{}", function_text(&name, &body)),
+                    Resolution::Builtin => println!("{}: shell built-in command", name),
+                    Resolution::File(path) => println!("{}", path.display()),
+                }
+            }
+        }
+
+        if missing {
+            Err(CommandError::Finished(1))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+pub struct Command;
+
+impl Builtin for Command {
+    fn name() -> &'static str {
+        "command"
+    }
+
+    fn about() -> &'static str {
+        "Run a builtin or program, skipping aliases and functions"
+    }
+
+    fn examples() -> [&'static str; 3] {
+        ["command ls", "command -v git", "command -V cd"]
+    }
+
+    fn run(shell: &mut Shell, args: Vec<String>) -> Result<(), CommandError> {
+        let (flags, mut rest) =
+            split_flags(args, "vV").map_err(|e| CommandError::Error(format!("command: {}", e)))?;
+
+        if rest.is_empty() {
+            return Ok(());
+        }
+
+        // Only builtins and files are candidates for `command`
+        let skip_aliases = |shell: &Shell, name: &str| {
+            shell
+                .resolve(name)
+                .into_iter()
+                .find(|r| !matches!(r, Resolution::Alias(_) | Resolution::Function(_)))
+        };
+
+        if flags.contains(&'v') || flags.contains(&'V') {
+            let verbose = flags.contains(&'V');
+            let mut missing = false;
+            for name in rest {
+                match (skip_aliases(shell, &name), verbose) {
+                    (Some(Resolution::File(path)), false) => println!("{}", path.display()),
+                    (Some(Resolution::File(path)), true) => {
+                        println!("{} is {}", name, path.display())
+                    }
+                    (Some(_), false) => println!("{}", name),
+                    (Some(_), true) => println!("{} is a shell builtin", name),
+                    (None, verbose) => {
+                        if verbose {
+                            eprintln!("vsh: command: {}: not found", name);
+                        }
+                        missing = true;
+                    }
+                }
+            }
+            return if missing {
+                Err(CommandError::Finished(1))
+            } else {
+                Ok(())
+            };
+        }
+
+        let keyword = rest.remove(0);
+        match builtins::lookup(&keyword) {
+            Some(handler) => handler(shell, rest),
+            None => Vshcommand::spawn(keyword, rest, shell),
+        }
+    }
+}
+
+pub struct RunBuiltin;
+
+impl Builtin for RunBuiltin {
+    fn name() -> &'static str {
+        "builtin"
+    }
+
+    fn about() -> &'static str {
+        "Run a shell builtin, even if an alias, function or program shares its name"
+    }
+
+    fn examples() -> [&'static str; 3] {
+        ["builtin cd ~", "builtin type ls", "builtin exit"]
+    }
+
+    fn run(shell: &mut Shell, mut args: Vec<String>) -> Result<(), CommandError> {
+        if args.is_empty() {
+            return Ok(());
+        }
+
+        let keyword = args.remove(0);
+        match builtins::lookup(&keyword) {
+            Some(handler) => handler(shell, args),
+            None => Err(CommandError::Error(format!(
+                "builtin: {}: not a shell builtin",
+                keyword
+            ))),
+        }
+    }
+}
@@ -0,0 +1,314 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+use std::io;
+
+use libc::c_int;
+
+use crate::builtins::split_flags;
+use crate::command::Builtin;
+use crate::eval::CommandError;
+use crate::shell::Shell;
+use crate::utils::{os_error_text, signal_list, signal_name, signal_number};
+
+fn job_id(name: &str, shell: &Shell, spec: &str) -> Result<usize, CommandError> {
+    shell
+        .jobs
+        .find(spec)
+        .ok_or_else(|| CommandError::Error(format!("{}: {}: no such job", name, spec)))
+}
+
+pub struct Jobs;
+
+impl Builtin for Jobs {
+    fn name() -> &'static str {
+        "jobs"
+    }
+
+    fn about() -> &'static str {
+        "List the background jobs of this shell"
+    }
+
+    fn examples() -> [&'static str; 3] {
+        ["jobs", "jobs -l", "jobs %1"]
+    }
+
+    fn run(shell: &mut Shell, args: Vec<String>) -> Result<(), CommandError> {
+        let (flags, specs) =
+            split_flags(args, "lp").map_err(|e| CommandError::Error(format!("jobs: {}", e)))?;
+
+        let ids = if specs.is_empty() {
+            shell.jobs.iter().map(|job| job.id).collect::<Vec<_>>()
+        } else {
+            specs
+                .iter()
+                .map(|spec| job_id(Self::name(), shell, spec))
+                .collect::<Result<Vec<_>, _>>()?
+        };
+
+        for id in ids {
+            let job = shell.jobs.get(id).unwrap();
+            if flags.contains(&'p') {
+                println!("{}", job.pid());
+            } else if flags.contains(&'l') {
+                println!(
+                    "[{}]{} {} {:<10} {}",
+                    job.id,
+                    shell.jobs.marker(id),
+                    job.pid(),
+                    job.state(),
+                    job.command
+                );
+            } else {
+                println!(
+                    "[{}]{}  {:<10} {}",
+                    job.id,
+                    shell.jobs.marker(id),
+                    job.state(),
+                    job.command
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct Kill;
+
+impl Kill {
+    // `kill -l 130` names the signal behind an exit status
+    fn list(args: &[String]) -> Result<(), CommandError> {
+        if args.is_empty() {
+            for (name, _) in signal_list() {
+                println!("{}", name);
+            }
+            return Ok(());
+        }
+
+        for arg in args {
+            let listed = match arg.parse::<c_int>() {
+                // Statuses above 128 are deaths by signal
+                Ok(number) if number > 128 => signal_name(number - 128).map(str::to_string),
+                Ok(number) => signal_name(number).map(str::to_string),
+                Err(_) => signal_number(arg).map(|number| number.to_string()),
+            };
+            match listed {
+                Some(listed) => println!("{}", listed),
+                None => {
+                    return Err(CommandError::Error(format!(
+                        "kill: {}: invalid signal specification",
+                        arg
+                    )))
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Builtin for Kill {
+    fn name() -> &'static str {
+        "kill"
+    }
+
+    fn about() -> &'static str {
+        "Send a signal to jobs or processes, TERM unless told otherwise"
+    }
+
+    fn examples() -> [&'static str; 3] {
+        ["kill %1", "kill -INT 4242", "kill -s HUP %vim"]
+    }
+
+    fn run(shell: &mut Shell, mut args: Vec<String>) -> Result<(), CommandError> {
+        let mut signal = libc::SIGTERM;
+
+        match args.first().map(String::as_str) {
+            Some("-l") | Some("-L") => return Self::list(&args[1..]),
+            Some("-s") | Some("-n") => {
+                if args.len() < 2 {
+                    return Err(CommandError::Error(
+                        "kill: option requires an argument".to_string(),
+                    ));
+                }
+                let spec = args.remove(1);
+                signal = signal_number(&spec).ok_or_else(|| {
+                    CommandError::Error(format!("kill: {}: invalid signal specification", spec))
+                })?;
+                args.remove(0);
+            }
+            Some("--") => {
+                args.remove(0);
+            }
+            Some(flag) if flag.starts_with('-') && flag.len() > 1 => {
+                signal = signal_number(&flag[1..]).ok_or_else(|| {
+                    CommandError::Error(format!(
+                        "kill: {}: invalid signal specification",
+                        &flag[1..]
+                    ))
+                })?;
+                args.remove(0);
+            }
+            _ => (),
+        }
+
+        if args.is_empty() {
+            return Err(CommandError::Error(
+                "kill: usage: kill [-s sigspec | -n signum | -sigspec] pid | jobspec ... or kill -l [sigspec]".to_string(),
+            ));
+        }
+
+        let mut failed = false;
+        for target in args {
+            // Jobs run in their own process group, the whole group gets the signal
+            let pid = if target.starts_with('%') {
+                match job_id(Self::name(), shell, &target) {
+                    Ok(id) => -(shell.jobs.get(id).unwrap().pid() as libc::pid_t),
+                    Err(CommandError::Error(e)) => {
+                        eprintln!("vsh: {}", e);
+                        failed = true;
+                        continue;
+                    }
+                    Err(e) => return Err(e),
+                }
+            } else {
+                match target.parse::<libc::pid_t>() {
+                    Ok(pid) => pid,
+                    Err(_) => {
+                        eprintln!(
+                            "vsh: kill: {}: arguments must be process or job IDs",
+                            target
+                        );
+                        failed = true;
+                        continue;
+                    }
+                }
+            };
+
+            if unsafe { libc::kill(pid, signal) } != 0 {
+                eprintln!(
+                    "vsh: kill: ({}) - {}",
+                    target,
+                    os_error_text(&io::Error::last_os_error())
+                );
+                failed = true;
+            }
+        }
+
+        if failed {
+            Err(CommandError::Finished(1))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+pub struct Wait;
+
+impl Builtin for Wait {
+    fn name() -> &'static str {
+        "wait"
+    }
+
+    fn about() -> &'static str {
+        "Wait for background jobs to finish, returns the status of the last one waited for"
+    }
+
+    fn examples() -> [&'static str; 3] {
+        ["wait", "wait %1", "wait $!"]
+    }
+
+    fn run(shell: &mut Shell, args: Vec<String>) -> Result<(), CommandError> {
+        // Without arguments every job is waited for and the status is always 0
+        if args.is_empty() {
+            let ids = shell.jobs.iter().map(|job| job.id).collect::<Vec<_>>();
+            for id in ids {
+                shell.jobs.wait(id);
+            }
+            return Ok(());
+        }
+
+        let mut status = 0;
+        for spec in args {
+            let finished = spec
+                .parse::<u32>()
+                .ok()
+                .and_then(|pid| shell.jobs.take_finished(pid));
+            status = match (shell.jobs.find(&spec), finished) {
+                (Some(id), _) => shell.jobs.wait(id).unwrap_or(127),
+                (None, Some(status)) => status,
+                (None, None) => {
+                    if spec.starts_with('%') {
+                        eprintln!("vsh: wait: {}: no such job", spec);
+                    } else {
+                        eprintln!("vsh: wait: pid {} is not a child of this shell", spec);
+                    }
+                    127
+                }
+            };
+        }
+
+        if status == 0 {
+            Ok(())
+        } else {
+            Err(CommandError::Finished(status))
+        }
+    }
+}
+
+pub struct Disown;
+
+impl Builtin for Disown {
+    fn name() -> &'static str {
+        "disown"
+    }
+
+    fn about() -> &'static str {
+        "Remove jobs from the job table so they are not sent SIGHUP when vsh exits"
+    }
+
+    fn examples() -> [&'static str; 3] {
+        ["disown", "disown %2", "disown -h %1"]
+    }
+
+    fn run(shell: &mut Shell, args: Vec<String>) -> Result<(), CommandError> {
+        let (flags, specs) =
+            split_flags(args, "ahr").map_err(|e| CommandError::Error(format!("disown: {}", e)))?;
+
+        let ids = if flags.contains(&'a') || flags.contains(&'r') {
+            shell
+                .jobs
+                .iter()
+                .filter(|job| !flags.contains(&'r') || job.status.is_none())
+                .map(|job| job.id)
+                .collect::<Vec<_>>()
+        } else if specs.is_empty() {
+            match shell.jobs.find("%+") {
+                Some(id) => vec![id],
+                None => {
+                    return Err(CommandError::Error(
+                        "disown: current: no such job".to_string(),
+                    ))
+                }
+            }
+        } else {
+            specs
+                .iter()
+                .map(|spec| job_id(Self::name(), shell, spec))
+                .collect::<Result<Vec<_>, _>>()?
+        };
+
+        for id in ids {
+            if flags.contains(&'h') {
+                if let Some(job) = shell.jobs.get_mut(id) {
+                    job.nohup = true;
+                }
+            } else {
+                shell.jobs.remove(id);
+            }
+        }
+        Ok(())
+    }
+}
@@ -4,4 +4,168 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/.
 */
 
+#[cfg(test)]
+pub mod builtins_test;
+
+pub mod alias;
 pub mod cd;
+pub mod config;
+pub mod exit;
+pub mod introspect;
+pub mod jobs;
+pub mod options;
+pub mod resource;
+pub mod source;
+pub mod trap;
+pub mod vars;
+
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+use toml::Value;
+use toml_edit::{Array, Document, InlineTable};
+
+use crate::command::Builtin;
+use crate::eval::CommandError;
+use crate::shell::Shell;
+use crate::utils::os_error_text;
+
+// Splits leading single letter flags like `-a` or `-ap` from the operands
+pub fn split_flags(args: Vec<String>, allowed: &str) -> Result<(Vec<char>, Vec<String>), String> {
+    let mut flags = Vec::new();
+    let mut rest = args.into_iter().peekable();
+
+    while let Some(arg) = rest.peek() {
+        if arg == "--" {
+            rest.next();
+            break;
+        }
+        if !arg.starts_with('-') || arg.len() == 1 {
+            break;
+        }
+        for flag in arg.chars().skip(1) {
+            if !allowed.contains(flag) {
+                return Err(format!("-{}: invalid option", flag));
+            }
+            flags.push(flag);
+        }
+        rest.next();
+    }
+
+    Ok((flags, rest.collect()))
+}
+
+// Takes `--save` out of the arguments, wherever it is
+pub fn take_save(args: Vec<String>) -> (bool, Vec<String>) {
+    let save = args.iter().any(|arg| arg == "--save");
+    (
+        save,
+        args.into_iter().filter(|arg| arg != "--save").collect(),
+    )
+}
+
+// A value of the `toml` crate as `toml_edit` writes it, tables become inline tables
+pub fn edit_value(value: &Value) -> toml_edit::Value {
+    match value {
+        Value::String(text) => text.into(),
+        Value::Integer(number) => (*number).into(),
+        Value::Float(number) => (*number).into(),
+        Value::Boolean(on) => (*on).into(),
+        Value::Datetime(date) => {
+            let date = date.to_string();
+            date.parse().unwrap_or_else(|_| date.into())
+        }
+        Value::Array(values) => values.iter().map(edit_value).collect::<Array>().into(),
+        Value::Table(table) => table
+            .iter()
+            .map(|(key, value)| (key.as_str(), edit_value(value)))
+            .collect::<InlineTable>()
+            .into(),
+    }
+}
+
+// A config file's text as a document that keeps its comments and formatting when changed
+pub fn parse_document(config: &str) -> Result<Document, String> {
+    config.parse::<Document>().map_err(|e| e.to_string())
+}
+
+// Rewrites the config file at `path` with `update`, a missing file counts as empty
+pub fn save_config(
+    path: &Path,
+    update: impl FnOnce(&str) -> Result<String, String>,
+) -> Result<(), String> {
+    let config = match fs::read_to_string(path) {
+        Ok(config) => config,
+        Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(format!("{}: {}", path.display(), os_error_text(&e))),
+    };
+    let config = update(&config)?;
+    fs::write(path, config).map_err(|e| format!("{}: {}", path.display(), os_error_text(&e)))
+}
+
+pub type Handler = fn(&mut Shell, Vec<String>) -> Result<(), CommandError>;
+
+// `<builtin> --help` is answered here so every builtin gets it for free
+fn invoke<B: Builtin>(shell: &mut Shell, args: Vec<String>) -> Result<(), CommandError> {
+    if args.len() == 1 && args[0] == "--help" {
+        println!("{}", B::help());
+        return Ok(());
+    }
+    B::run(shell, args)
+}
+
+fn table() -> Vec<(&'static str, Handler)> {
+    use alias::{Abbr, Alias, Unalias};
+    use cd::Cd;
+    use config::{ConfigBuiltin, Reload};
+    use exit::{Exit, Return};
+    use introspect::{Command, RunBuiltin, Type, Which};
+    use jobs::{Disown, Jobs, Kill, Wait};
+    use options::{Set, Shopt};
+    use resource::{Times, Ulimit, Umask};
+    use source::{Eval, Source};
+    use trap::Trap;
+    use vars::{Export, Unset};
+
+    vec![
+        (".", invoke::<Source>),
+        (Abbr::name(), invoke::<Abbr>),
+        (Alias::name(), invoke::<Alias>),
+        (RunBuiltin::name(), invoke::<RunBuiltin>),
+        (Cd::name(), invoke::<Cd>),
+        (Command::name(), invoke::<Command>),
+        (ConfigBuiltin::name(), invoke::<ConfigBuiltin>),
+        (Disown::name(), invoke::<Disown>),
+        (Eval::name(), invoke::<Eval>),
+        (Exit::name(), invoke::<Exit>),
+        (Export::name(), invoke::<Export>),
+        (Jobs::name(), invoke::<Jobs>),
+        (Kill::name(), invoke::<Kill>),
+        (Reload::name(), invoke::<Reload>),
+        (Return::name(), invoke::<Return>),
+        (Set::name(), invoke::<Set>),
+        (Shopt::name(), invoke::<Shopt>),
+        (Source::name(), invoke::<Source>),
+        (Times::name(), invoke::<Times>),
+        (Trap::name(), invoke::<Trap>),
+        (Type::name(), invoke::<Type>),
+        (Ulimit::name(), invoke::<Ulimit>),
+        (Umask::name(), invoke::<Umask>),
+        (Unalias::name(), invoke::<Unalias>),
+        (Unset::name(), invoke::<Unset>),
+        (Wait::name(), invoke::<Wait>),
+        (Which::name(), invoke::<Which>),
+    ]
+}
+
+pub fn names() -> Vec<&'static str> {
+    table().into_iter().map(|(name, _)| name).collect()
+}
+
+pub fn lookup(name: &str) -> Option<Handler> {
+    table()
+        .into_iter()
+        .find(|(builtin, _)| *builtin == name)
+        .map(|(_, handler)| handler)
+}
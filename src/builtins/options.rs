@@ -0,0 +1,173 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+use std::env;
+
+use crate::builtins::split_flags;
+use crate::command::Builtin;
+use crate::eval::CommandError;
+use crate::lexer::quote_word;
+use crate::options::{parse_set_args, SET_OPTIONS, SHOPT_OPTIONS};
+use crate::shell::Shell;
+
+fn on_off(on: bool) -> &'static str {
+    if on {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+pub struct Set;
+
+impl Builtin for Set {
+    fn name() -> &'static str {
+        "set"
+    }
+
+    fn about() -> &'static str {
+        "Turn shell options on with -o or off with +o, or set the positional parameters"
+    }
+
+    fn examples() -> [&'static str; 3] {
+        ["set -o", "set -eu -o pipefail", "set -- one two three"]
+    }
+
+    fn run(shell: &mut Shell, args: Vec<String>) -> Result<(), CommandError> {
+        match args
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .as_slice()
+        {
+            [] => {
+                let mut vars = env::vars().collect::<Vec<_>>();
+                vars.sort();
+                for (name, value) in vars {
+                    println!("{}={}", name, quote_word(&value));
+                }
+                return Ok(());
+            }
+            ["-o"] => {
+                for (name, _) in SET_OPTIONS {
+                    println!("{:<15}\t{}", name, on_off(shell.options.is_set(name)));
+                }
+                return Ok(());
+            }
+            // Prints commands that restore the current options
+            ["+o"] => {
+                for (name, _) in SET_OPTIONS {
+                    let sign = if shell.options.is_set(name) { '-' } else { '+' };
+                    println!("set {}o {}", sign, name);
+                }
+                return Ok(());
+            }
+            _ => (),
+        }
+
+        let (toggles, positional) =
+            parse_set_args(args).map_err(|e| CommandError::Error(format!("set: {}", e)))?;
+        for (name, on) in toggles {
+            shell.options.set(name, on).unwrap();
+        }
+        if let Some(positional) = positional {
+            shell.positional = positional;
+        }
+        Ok(())
+    }
+}
+
+pub struct Shopt;
+
+impl Builtin for Shopt {
+    fn name() -> &'static str {
+        "shopt"
+    }
+
+    fn about() -> &'static str {
+        "Turn shell options on with -s or off with -u, or show them; -o works on `set -o` options"
+    }
+
+    fn examples() -> [&'static str; 3] {
+        ["shopt", "shopt -s dotglob nullglob", "shopt -q nullglob"]
+    }
+
+    fn run(shell: &mut Shell, args: Vec<String>) -> Result<(), CommandError> {
+        let (flags, names) =
+            split_flags(args, "supqo").map_err(|e| CommandError::Error(format!("shopt: {}", e)))?;
+        if flags.contains(&'s') && flags.contains(&'u') {
+            return Err(CommandError::Error(
+                "shopt: cannot set and unset shell options simultaneously".to_string(),
+            ));
+        }
+
+        let known = if flags.contains(&'o') {
+            SET_OPTIONS
+                .iter()
+                .map(|(name, _)| *name)
+                .collect::<Vec<_>>()
+        } else {
+            SHOPT_OPTIONS.to_vec()
+        };
+        for name in &names {
+            if !known.contains(&name.as_str()) {
+                return Err(CommandError::Error(format!(
+                    "shopt: {}: invalid shell option name",
+                    name
+                )));
+            }
+        }
+
+        if !names.is_empty() && (flags.contains(&'s') || flags.contains(&'u')) {
+            for name in names {
+                shell.options.set(&name, flags.contains(&'s')).unwrap();
+            }
+            return Ok(());
+        }
+
+        // Only asking about particular options fails when one of them is off
+        let asked = !names.is_empty();
+        // Without names every option is shown, -s and -u narrow that down to those on or off
+        let shown = if names.is_empty() {
+            known
+                .into_iter()
+                .filter(|name| {
+                    let on = shell.options.is_set(name);
+                    !(flags.contains(&'s') && !on || flags.contains(&'u') && on)
+                })
+                .map(str::to_string)
+                .collect()
+        } else {
+            names
+        };
+
+        let mut all_on = true;
+        for name in shown {
+            let on = shell.options.is_set(&name);
+            all_on &= on;
+            if flags.contains(&'q') {
+                continue;
+            }
+            if flags.contains(&'p') {
+                let flag = match (flags.contains(&'o'), on) {
+                    (true, true) => "set -o",
+                    (true, false) => "set +o",
+                    (false, true) => "shopt -s",
+                    (false, false) => "shopt -u",
+                };
+                println!("{} {}", flag, name);
+            } else {
+                println!("{:<15}\t{}", name, on_off(on));
+            }
+        }
+
+        if all_on || !asked {
+            Ok(())
+        } else {
+            Err(CommandError::Finished(1))
+        }
+    }
+}
@@ -0,0 +1,288 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+use std::io;
+
+use crate::builtins::split_flags;
+use crate::command::Builtin;
+use crate::eval::CommandError;
+use crate::shell::Shell;
+use crate::utils::{cpu_time, format_duration, os_error_text};
+
+// Parses an octal mask like `022`, or a symbolic mode like `u=rwx,g=rx,o=` or `g-w`
+// Symbolic modes describe the permissions to allow, the result is still a mask
+pub fn parse_umask(spec: &str, current: u32) -> Result<u32, String> {
+    if spec.chars().all(|c| c.is_digit(8)) {
+        return match u32::from_str_radix(spec, 8) {
+            Ok(mask) if mask <= 0o777 => Ok(mask),
+            _ => Err(format!("{}: octal number out of range", spec)),
+        };
+    }
+
+    let mut allowed = !current & 0o777;
+    for clause in spec.split(',') {
+        let op_at = clause
+            .find(|c| "+-=".contains(c))
+            .ok_or_else(|| format!("{}: invalid symbolic mode", spec))?;
+        let (who, rest) = clause.split_at(op_at);
+        let (op, perms) = rest.split_at(1);
+
+        let mut who_bits = 0;
+        for c in who.chars() {
+            who_bits |= match c {
+                'u' => 0o700,
+                'g' => 0o070,
+                'o' => 0o007,
+                'a' => 0o777,
+                _ => return Err(format!("{}: invalid symbolic mode", spec)),
+            };
+        }
+        if who_bits == 0 {
+            who_bits = 0o777;
+        }
+
+        let mut perm_bits = 0;
+        for c in perms.chars() {
+            perm_bits |= match c {
+                'r' => 0o444,
+                'w' => 0o222,
+                'x' => 0o111,
+                _ => return Err(format!("{}: invalid symbolic mode", spec)),
+            };
+        }
+
+        let bits = who_bits & perm_bits;
+        allowed = match op {
+            "+" => allowed | bits,
+            "-" => allowed & !bits,
+            _ => (allowed & !who_bits) | bits,
+        };
+    }
+    Ok(!allowed & 0o777)
+}
+
+// `u=rwx,g=rx,o=rx` for a mask of 022
+pub fn symbolic_umask(mask: u32) -> String {
+    let allowed = !mask & 0o777;
+    ["u", "g", "o"]
+        .iter()
+        .enumerate()
+        .map(|(i, who)| {
+            let bits = (allowed >> (6 - 3 * i)) & 0o7;
+            let perms = [(0o4, 'r'), (0o2, 'w'), (0o1, 'x')]
+                .iter()
+                .filter(|(bit, _)| bits & bit != 0)
+                .map(|(_, c)| *c)
+                .collect::<String>();
+            format!("{}={}", who, perms)
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn current_umask() -> u32 {
+    // The only way to read the mask is to set it, so put it straight back
+    unsafe {
+        let mask = libc::umask(0);
+        libc::umask(mask);
+        mask as u32
+    }
+}
+
+pub struct Umask;
+
+impl Builtin for Umask {
+    fn name() -> &'static str {
+        "umask"
+    }
+
+    fn about() -> &'static str {
+        "Show or set the file creation mask, in octal or symbolic form"
+    }
+
+    fn examples() -> [&'static str; 3] {
+        ["umask -S", "umask 077", "umask u=rwx,g=rx,o="]
+    }
+
+    fn run(_shell: &mut Shell, args: Vec<String>) -> Result<(), CommandError> {
+        let (flags, operands) =
+            split_flags(args, "Sp").map_err(|e| CommandError::Error(format!("umask: {}", e)))?;
+
+        match operands.first() {
+            Some(spec) => {
+                let mask = parse_umask(spec, current_umask())
+                    .map_err(|e| CommandError::Error(format!("umask: {}", e)))?;
+                unsafe {
+                    libc::umask(mask as libc::mode_t);
+                }
+            }
+            None => {
+                let mask = current_umask();
+                let shown = if flags.contains(&'S') {
+                    symbolic_umask(mask)
+                } else {
+                    format!("{:04o}", mask)
+                };
+                if flags.contains(&'p') {
+                    println!(
+                        "umask {}{}",
+                        if flags.contains(&'S') { "-S " } else { "" },
+                        shown
+                    );
+                } else {
+                    println!("{}", shown);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+type Resource = libc::__rlimit_resource_t;
+#[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+type Resource = libc::c_int;
+
+// Flag, resource, unit in bytes and description, in the order `ulimit -a` prints them
+const LIMITS: &[(char, Resource, libc::rlim_t, &str)] = &[
+    ('c', libc::RLIMIT_CORE, 512, "core file size (blocks)"),
+    ('f', libc::RLIMIT_FSIZE, 512, "file size (blocks)"),
+    ('n', libc::RLIMIT_NOFILE, 1, "open files"),
+    ('s', libc::RLIMIT_STACK, 1024, "stack size (kbytes)"),
+    ('u', libc::RLIMIT_NPROC, 1, "max user processes"),
+    ('v', libc::RLIMIT_AS, 1024, "virtual memory (kbytes)"),
+];
+
+fn get_limit(resource: Resource) -> io::Result<libc::rlimit> {
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    if unsafe { libc::getrlimit(resource, &mut limit) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(limit)
+}
+
+fn show_limit(value: libc::rlim_t, unit: libc::rlim_t) -> String {
+    if value == libc::RLIM_INFINITY {
+        String::from("unlimited")
+    } else {
+        (value / unit).to_string()
+    }
+}
+
+pub struct Ulimit;
+
+impl Builtin for Ulimit {
+    fn name() -> &'static str {
+        "ulimit"
+    }
+
+    fn about() -> &'static str {
+        "Show or set resource limits of the shell and the programs it starts"
+    }
+
+    fn examples() -> [&'static str; 3] {
+        ["ulimit -a", "ulimit -n 4096", "ulimit -H -c unlimited"]
+    }
+
+    fn run(_shell: &mut Shell, args: Vec<String>) -> Result<(), CommandError> {
+        let error = |e: String| CommandError::Error(format!("ulimit: {}", e));
+        let (flags, operands) = split_flags(args, "SHacfnsuv").map_err(error)?;
+
+        let soft = flags.contains(&'S');
+        let hard = flags.contains(&'H');
+        // Reading shows the soft limit unless asked otherwise, setting changes both
+        let show_hard = hard && !soft;
+
+        if flags.contains(&'a') {
+            for (flag, resource, unit, description) in LIMITS {
+                let limit = get_limit(*resource).map_err(|e| error(os_error_text(&e)))?;
+                let value = if show_hard {
+                    limit.rlim_max
+                } else {
+                    limit.rlim_cur
+                };
+                println!(
+                    "{:<28}(-{}) {}",
+                    description,
+                    flag,
+                    show_limit(value, *unit)
+                );
+            }
+            return Ok(());
+        }
+
+        let (_, resource, unit, _) = LIMITS
+            .iter()
+            .find(|(flag, ..)| flags.contains(flag))
+            .unwrap_or(&LIMITS[1]);
+        let mut limit = get_limit(*resource).map_err(|e| error(os_error_text(&e)))?;
+
+        let value = match operands.first() {
+            None => {
+                let value = if show_hard {
+                    limit.rlim_max
+                } else {
+                    limit.rlim_cur
+                };
+                println!("{}", show_limit(value, *unit));
+                return Ok(());
+            }
+            Some(value) if value == "unlimited" => libc::RLIM_INFINITY,
+            Some(value) if value == "hard" => limit.rlim_max,
+            Some(value) if value == "soft" => limit.rlim_cur,
+            Some(value) => match value
+                .parse::<libc::rlim_t>()
+                .ok()
+                .and_then(|n| n.checked_mul(*unit))
+            {
+                Some(n) => n,
+                None => return Err(error(format!("{}: invalid number", value))),
+            },
+        };
+
+        if soft || !hard {
+            limit.rlim_cur = value;
+        }
+        if hard || !soft {
+            limit.rlim_max = value;
+        }
+        if unsafe { libc::setrlimit(*resource, &limit) } != 0 {
+            return Err(error(os_error_text(&io::Error::last_os_error())));
+        }
+        Ok(())
+    }
+}
+
+pub struct Times;
+
+impl Builtin for Times {
+    fn name() -> &'static str {
+        "times"
+    }
+
+    fn about() -> &'static str {
+        "Show the user and system time used by the shell, then by its children"
+    }
+
+    fn examples() -> [&'static str; 3] {
+        ["times", "make; times", "trap times EXIT"]
+    }
+
+    fn run(_shell: &mut Shell, _args: Vec<String>) -> Result<(), CommandError> {
+        for who in [libc::RUSAGE_SELF, libc::RUSAGE_CHILDREN] {
+            let (user, system) = cpu_time(who);
+            println!(
+                "{} {}",
+                format_duration(user, 3, true),
+                format_duration(system, 3, true)
+            );
+        }
+        Ok(())
+    }
+}
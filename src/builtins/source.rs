@@ -0,0 +1,89 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+use std::fs;
+
+use crate::command::Builtin;
+use crate::eval::{catch_return, CommandError, Vshcommand};
+use crate::shell::{Shell, MAX_DEPTH};
+use crate::utils::os_error_text;
+
+pub struct Source;
+
+impl Builtin for Source {
+    fn name() -> &'static str {
+        "source"
+    }
+
+    fn about() -> &'static str {
+        "Run the commands of a file in the current shell, also available as `.`"
+    }
+
+    fn examples() -> [&'static str; 3] {
+        [
+            "source ~/.config/vsh/aliases.vsh",
+            ". ./env.vsh",
+            "source setup.vsh --verbose",
+        ]
+    }
+
+    fn run(shell: &mut Shell, mut args: Vec<String>) -> Result<(), CommandError> {
+        if args.is_empty() {
+            return Err(CommandError::Error(
+                "source: filename argument required".to_string(),
+            ));
+        }
+
+        if shell.depth >= MAX_DEPTH {
+            return Err(CommandError::Error(
+                "source: maximum nesting level exceeded".to_string(),
+            ));
+        }
+        let file = args.remove(0);
+        let script = fs::read_to_string(&file)
+            .map_err(|e| CommandError::Error(format!("source: {}: {}", file, os_error_text(&e))))?;
+
+        // Without arguments the file sees the positional parameters of its caller
+        let saved = if args.is_empty() {
+            None
+        } else {
+            Some(std::mem::replace(&mut shell.positional, args))
+        };
+
+        shell.depth += 1;
+        let result = Vshcommand::run_script(&script, &file, shell);
+        shell.depth -= 1;
+
+        if let Some(saved) = saved {
+            shell.positional = saved;
+        }
+        catch_return(result)
+    }
+}
+
+pub struct Eval;
+
+impl Builtin for Eval {
+    fn name() -> &'static str {
+        "eval"
+    }
+
+    fn about() -> &'static str {
+        "Join the arguments into a single command line and run it"
+    }
+
+    fn examples() -> [&'static str; 3] {
+        [
+            "eval 'echo $HOME'",
+            "eval $CMD --verbose",
+            "eval \"cd $DIR && ls\"",
+        ]
+    }
+
+    fn run(shell: &mut Shell, args: Vec<String>) -> Result<(), CommandError> {
+        Vshcommand::run(args.join(" "), shell)
+    }
+}
@@ -0,0 +1,123 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+use crate::builtins::split_flags;
+use crate::command::Builtin;
+use crate::eval::CommandError;
+use crate::shell::Shell;
+use crate::utils::{signal_list, signal_name, signal_number, TRAPPABLE};
+
+// EXIT and ERR are not real signals, vsh raises them itself
+const PSEUDO_SIGNALS: &[&str] = &["EXIT", "ERR"];
+
+// Name a trap is stored under, `sigint`, `INT` and `2` all become `INT`
+fn trap_name(spec: &str) -> Result<String, CommandError> {
+    if spec == "0" {
+        return Ok("EXIT".to_string());
+    }
+    let upper = spec.to_uppercase();
+    if PSEUDO_SIGNALS.contains(&upper.as_str()) {
+        return Ok(upper);
+    }
+
+    match signal_number(spec) {
+        Some(number) if TRAPPABLE.contains(&number) => Ok(signal_name(number).unwrap().to_string()),
+        Some(_) => Err(CommandError::Error(format!(
+            "trap: {}: signal cannot be trapped",
+            spec
+        ))),
+        None => Err(CommandError::Error(format!(
+            "trap: {}: invalid signal specification",
+            spec
+        ))),
+    }
+}
+
+fn print_trap(name: &str, command: &str) {
+    let name = if PSEUDO_SIGNALS.contains(&name) {
+        name.to_string()
+    } else {
+        format!("SIG{}", name)
+    };
+    println!("trap -- '{}' {}", command.replace('\'', "'\\''"), name);
+}
+
+pub struct Trap;
+
+impl Builtin for Trap {
+    fn name() -> &'static str {
+        "trap"
+    }
+
+    fn about() -> &'static str {
+        "Run a command when the shell receives a signal, exits (EXIT) or a command fails (ERR)"
+    }
+
+    fn examples() -> [&'static str; 3] {
+        [
+            "trap 'echo bye' EXIT",
+            "trap 'rm -f /tmp/lock' INT TERM HUP",
+            "trap - INT",
+        ]
+    }
+
+    fn run(shell: &mut Shell, args: Vec<String>) -> Result<(), CommandError> {
+        let (flags, mut operands) =
+            split_flags(args, "lp").map_err(|e| CommandError::Error(format!("trap: {}", e)))?;
+
+        if flags.contains(&'l') {
+            let list = signal_list();
+            for row in list.chunks(5) {
+                let row = row
+                    .iter()
+                    .map(|(name, number)| format!("{:>2}) SIG{:<8}", number, name))
+                    .collect::<Vec<_>>();
+                println!("{}", row.join(" ").trim_end());
+            }
+            return Ok(());
+        }
+
+        if operands.is_empty() || flags.contains(&'p') {
+            let mut names = if operands.is_empty() {
+                shell.traps.keys().cloned().collect::<Vec<_>>()
+            } else {
+                operands
+                    .iter()
+                    .map(|spec| trap_name(spec))
+                    .collect::<Result<Vec<_>, _>>()?
+            };
+            names.sort();
+            for name in names {
+                if let Some(command) = shell.traps.get(&name) {
+                    print_trap(&name, command);
+                }
+            }
+            return Ok(());
+        }
+
+        // A lone signal, or `-` as the command, puts the default action back
+        let command = if operands.len() == 1 || operands[0] == "-" {
+            if operands[0] == "-" {
+                operands.remove(0);
+            }
+            None
+        } else {
+            Some(operands.remove(0))
+        };
+
+        for spec in operands {
+            let name = trap_name(&spec)?;
+            if let Some(number) = signal_number(&name) {
+                shell.signals.set_trapped(number, command.is_some());
+            }
+            match &command {
+                Some(command) => shell.traps.insert(name, command.to_string()),
+                None => shell.traps.remove(&name),
+            };
+        }
+        Ok(())
+    }
+}
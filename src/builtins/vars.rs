@@ -0,0 +1,99 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+use std::env;
+
+use crate::builtins::split_flags;
+use crate::command::Builtin;
+use crate::eval::CommandError;
+use crate::lexer::{quote_word, split_assignment};
+use crate::shell::Shell;
+use crate::utils::check_env_value;
+
+// vsh keeps its variables in the environment, so every variable is exported
+pub struct Export;
+
+impl Builtin for Export {
+    fn name() -> &'static str {
+        "export"
+    }
+
+    fn about() -> &'static str {
+        "Set variables, or list them all when called without arguments"
+    }
+
+    fn examples() -> [&'static str; 3] {
+        [
+            "export",
+            "export EDITOR=vim",
+            "export PATH=~/.cargo/bin:$PATH",
+        ]
+    }
+
+    fn run(_shell: &mut Shell, args: Vec<String>) -> Result<(), CommandError> {
+        if args.is_empty() {
+            let mut vars = env::vars().collect::<Vec<_>>();
+            vars.sort();
+            for (name, value) in vars {
+                println!("export {}={}", name, quote_word(&value));
+            }
+            return Ok(());
+        }
+
+        for arg in args {
+            match split_assignment(&arg) {
+                Some((name, value)) => {
+                    check_env_value(name, value)
+                        .map_err(|e| CommandError::Error(format!("export: {}", e)))?;
+                    env::set_var(name, value)
+                }
+                None if split_assignment(&format!("{}=", arg)).is_some() => (),
+                None => {
+                    return Err(CommandError::Error(format!(
+                        "export: `{}`: not a valid identifier",
+                        arg
+                    )))
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct Unset;
+
+impl Builtin for Unset {
+    fn name() -> &'static str {
+        "unset"
+    }
+
+    fn about() -> &'static str {
+        "Remove variables, or functions with -f"
+    }
+
+    fn examples() -> [&'static str; 3] {
+        ["unset EDITOR", "unset -v A B", "unset -f greet"]
+    }
+
+    fn run(shell: &mut Shell, args: Vec<String>) -> Result<(), CommandError> {
+        let (flags, names) =
+            split_flags(args, "fv").map_err(|e| CommandError::Error(format!("unset: {}", e)))?;
+
+        for name in names {
+            if flags.contains(&'f') {
+                shell.functions.remove(&name);
+            } else if split_assignment(&format!("{}=", name)) == Some((&name, "")) {
+                env::remove_var(&name);
+            } else {
+                return Err(CommandError::Error(format!(
+                    "unset: `{}`: not a valid identifier",
+                    name
+                )));
+            }
+        }
+        Ok(())
+    }
+}
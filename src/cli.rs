@@ -0,0 +1,109 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use std::path::PathBuf;
+
+use crate::options::parse_set_args;
+
+pub const USAGE: &str = "Usage: vsh [options] [-c command [arg ...]]";
+
+pub const HELP: &str = "Options:
+  -c <command>       Run the command and exit, the arguments after it become $1, $2, ...
+  -i                 Run interactively even when stdin is not a terminal
+  -l, --login        Run as a login shell, which runs profile.vsh first
+  --norc             Do not run init.vsh
+  --rcfile <file>    Run <file> instead of init.vsh
+  --config <path>    Read the config from <path> instead of ~/.config/vsh/config.toml
+  --profile <name>   Lay [profile.<name>] of the config over the rest, like $VSH_PROFILE
+  --theme <theme>    Use <theme> as the prompt theme
+  --allow-insecure-config
+                     Load config and startup files other users can change, which are skipped
+  --no-history       Neither read nor save the command history
+  --debug-ast        Print how each command line is parsed before running it
+  --check-config     Check the config file and the theme, and exit with 1 if anything is wrong
+  --print-config     Print the config merged from every file, with the file of each value
+  -e, -u, -x, -o <option>, +o <option>, ...
+                     Turn `set` options on, or off with +
+  --help             Show this help
+  --version          Show the version of vsh";
+
+// How vsh was started, from argv
+#[derive(Debug, Default, PartialEq)]
+pub struct Args {
+    // `set -o` options like `-eu -o pipefail`, they win over the ones in the config file
+    pub options: Vec<(&'static str, bool)>,
+    pub help: bool,
+    pub version: bool,
+    // The command of `-c`, and the positional parameters that follow it
+    pub command: Option<String>,
+    pub positional: Vec<String>,
+    pub interactive: bool,
+    // Login shells run profile.vsh first
+    pub login: bool,
+    // Skip init.vsh
+    pub norc: bool,
+    // Run this instead of init.vsh
+    pub rcfile: Option<PathBuf>,
+    pub config: Option<PathBuf>,
+    // The config profile to use, instead of the ones matching this machine
+    pub profile: Option<String>,
+    pub theme: Option<String>,
+    // Load config and startup files that are world-writable or owned by someone else
+    pub allow_insecure_config: bool,
+    pub no_history: bool,
+    pub debug_ast: bool,
+    pub check_config: bool,
+    pub print_config: bool,
+}
+
+// The argument a flag like `--config <path>` takes
+fn value(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<String, String> {
+    args.next()
+        .ok_or_else(|| format!("{}: option requires an argument", flag))
+}
+
+// Reads the arguments after argv[0], a login shell is also one whose argv[0] starts with `-`
+pub fn parse_args(argv0: &str, args: Vec<String>) -> Result<Args, String> {
+    let mut parsed = Args {
+        login: argv0.starts_with('-'),
+        ..Args::default()
+    };
+    let mut set_args = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--help" => parsed.help = true,
+            "--version" => parsed.version = true,
+            "-c" => {
+                parsed.command = Some(value(&mut args, &arg)?);
+                parsed.positional = args.by_ref().collect();
+            }
+            "-i" => parsed.interactive = true,
+            "-l" | "--login" => parsed.login = true,
+            "--norc" => parsed.norc = true,
+            "--rcfile" => parsed.rcfile = Some(PathBuf::from(value(&mut args, &arg)?)),
+            "--config" => parsed.config = Some(PathBuf::from(value(&mut args, &arg)?)),
+            "--profile" => parsed.profile = Some(value(&mut args, &arg)?),
+            "--theme" => parsed.theme = Some(value(&mut args, &arg)?),
+            "--allow-insecure-config" => parsed.allow_insecure_config = true,
+            "--no-history" => parsed.no_history = true,
+            "--debug-ast" => parsed.debug_ast = true,
+            "--check-config" => parsed.check_config = true,
+            "--print-config" => parsed.print_config = true,
+            _ if arg.starts_with("--") && arg != "--" => {
+                return Err(format!("{}: invalid option", arg))
+            }
+            _ => set_args.push(arg),
+        }
+    }
+
+    parsed.options = match parse_set_args(set_args)? {
+        (toggles, None) => toggles,
+        (toggles, Some(rest)) if rest.is_empty() => toggles,
+        (_, Some(rest)) => return Err(format!("{}: unexpected argument", rest[0])),
+    };
+    Ok(parsed)
+}
@@ -0,0 +1,65 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::cli::*;
+
+    fn strings(v: &[&str]) -> Vec<String> {
+        v.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_args_test() {
+        let args = parse_args("vsh", strings(&["-e", "--norc", "-o", "pipefail"])).unwrap();
+        assert_eq!(args.options, vec![("errexit", true), ("pipefail", true)]);
+        assert!(args.norc);
+        assert!(!args.login);
+
+        let args = parse_args("-vsh", strings(&["--rcfile", "/tmp/rc.vsh"])).unwrap();
+        assert!(args.login);
+        assert_eq!(args.rcfile, Some(PathBuf::from("/tmp/rc.vsh")));
+        assert!(parse_args("vsh", strings(&["-l"])).unwrap().login);
+
+        assert!(parse_args("vsh", strings(&["--rcfile"])).is_err());
+        assert!(parse_args("vsh", strings(&["script.vsh"])).is_err());
+        assert!(parse_args("vsh", strings(&["-q"])).is_err());
+    }
+
+    #[test]
+    fn parse_flags_test() {
+        let args = parse_args(
+            "vsh",
+            strings(&[
+                "--config", "a.toml", "--theme", "`> `", "-x", "-c", "echo $1", "one", "-e",
+            ]),
+        )
+        .unwrap();
+        assert_eq!(args.config, Some(PathBuf::from("a.toml")));
+        assert_eq!(args.theme.as_deref(), Some("`> `"));
+        assert_eq!(args.command.as_deref(), Some("echo $1"));
+        assert_eq!(args.positional, strings(&["one", "-e"]));
+        assert_eq!(args.options, vec![("xtrace", true)]);
+
+        let args = parse_args(
+            "vsh",
+            strings(&["-i", "--no-history", "--debug-ast", "--help"]),
+        )
+        .unwrap();
+        assert!(args.interactive && args.no_history && args.debug_ast && args.help);
+        assert!(parse_args("vsh", strings(&["--version"])).unwrap().version);
+        assert!(
+            parse_args("vsh", strings(&["--check-config"]))
+                .unwrap()
+                .check_config
+        );
+
+        assert!(parse_args("vsh", strings(&["--nonsense"])).is_err());
+        assert!(parse_args("vsh", strings(&["-c"])).is_err());
+        assert!(parse_args("vsh", strings(&["--config"])).is_err());
+    }
+}
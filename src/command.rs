@@ -5,6 +5,7 @@
 */
 
 use crate::eval::CommandError;
+use crate::shell::Shell;
 
 pub trait Builtin {
     fn name() -> &'static str;
@@ -13,9 +14,9 @@ pub trait Builtin {
 
     fn examples() -> [&'static str; 3];
 
-    fn run(args: Vec<String>) -> Result<(), CommandError>;
+    fn run(shell: &mut Shell, args: Vec<String>) -> Result<(), CommandError>;
 
-    fn help(&self) -> String {
+    fn help() -> String {
         format!(
             "{}\nAbout: \n{}\nExamples: \n1. {}\n2. {}\n3. {}",
             Self::name(),
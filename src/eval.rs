@@ -6,11 +6,28 @@
 
 use crate::builtins;
 use crate::command::Builtin;
-use crate::utils::expand;
+use crate::lexer::{
+    complete_commands, expand_aliases, expand_word, parse_ast, quote_word, split_assignment,
+    split_list, split_redirects, split_unquoted, split_words, strip_comments, Redirect,
+    RedirectKind,
+};
+use crate::shell::{Resolution, Shell, MAX_DEPTH};
+use crate::utils::{
+    check_env_value, closest, cpu_time, find_in_path, format_time, os_error_text, path_executables,
+    signal_name, timeval, SignalQueue, DEFAULT_TIMEFORMAT, POSIX_TIMEFORMAT, TRAPPABLE,
+};
 
-use std::collections::HashMap;
-use std::process::{Child, Command, Stdio};
-use std::string::ToString;
+use std::env;
+use std::ffi::CStr;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::os::unix::io::IntoRawFd;
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::path::Path;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::time::Instant;
+
+use lazy_static::lazy_static;
 
 pub struct Vshcommand {
     keyword: String,
@@ -19,120 +36,792 @@ pub struct Vshcommand {
 
 pub enum CommandError {
     Error(String),
-    Exit,
+    Exit(i32),
+    Return(i32),     // Unwinds to the innermost function or sourced file
     Finished(i32),   // If the program finished with a non-zero exit code
-    Terminated(i32), // If the program was terminated by the user
+    Terminated(i32), // If the program was killed by a signal, holds 128 + the signal
+}
+
+impl CommandError {
+    // The exit status this error leaves in `$?`
+    pub fn status(&self) -> i32 {
+        match self {
+            CommandError::Error(_) => 1,
+            CommandError::Exit(code)
+            | CommandError::Return(code)
+            | CommandError::Finished(code)
+            | CommandError::Terminated(code) => *code,
+        }
+    }
+}
+
+pub fn status_of(result: &Result<(), CommandError>) -> i32 {
+    match result {
+        Ok(()) => 0,
+        Err(e) => e.status(),
+    }
+}
+
+// Turns the `return` of a function or sourced file into its exit status
+pub fn catch_return(result: Result<(), CommandError>) -> Result<(), CommandError> {
+    match result {
+        Err(CommandError::Return(0)) => Ok(()),
+        Err(CommandError::Return(code)) => Err(CommandError::Finished(code)),
+        other => other,
+    }
 }
 
 impl Vshcommand {
     pub fn new(input: String) -> Self {
-        let mut splitted = input.trim().split_whitespace();
-        let keyword = match splitted.next() {
-            Some(x) => x.to_string(),
-            None => String::from(""),
-        };
+        let mut splitted = split_words(&input).into_iter();
+        let keyword = splitted.next().unwrap_or_default();
 
         Self {
             keyword,
-            args: splitted.map(ToString::to_string).collect::<Vec<String>>(),
+            args: splitted.collect::<Vec<String>>(),
         }
     }
 
-    pub fn eval(raw: String, aliases: &HashMap<&str, &str>) -> Result<(), CommandError> {
+    // Matches `name() { body }`, the body may span several lines
+    fn function_definition(raw: &str) -> Option<(String, String)> {
+        lazy_static! {
+            static ref RE: fancy_regex::Regex =
+                fancy_regex::Regex::new(r"(?s)^\s*([A-Za-z_][\w-]*)\s*\(\s*\)\s*\{(.*)\}\s*$")
+                    .unwrap();
+        }
+        let captures = RE.captures(raw).ok()??;
+        // The lines of the body stay as they were written, so `type` can show them
+        let body = captures[2].trim_end().trim_start_matches([' ', '\t']);
+        let body = body.strip_prefix('\n').unwrap_or(body);
+        Some((captures[1].to_string(), body.to_string()))
+    }
+
+    pub fn eval(raw: String, shell: &mut Shell) -> Result<(), CommandError> {
+        if let Some((name, body)) = Self::function_definition(&raw) {
+            shell.functions.insert(name, body);
+            return Ok(());
+        }
+
+        // `{ a; b; }` runs its commands in the current shell, as one command
+        if let Some(inner) = Self::group(&raw) {
+            return Self::run(inner.to_string(), shell);
+        }
+
         let vshcmd = Self::new(raw);
 
         match (vshcmd.keyword.as_str(), vshcmd.args.clone()) {
-            ("cd", y) => builtins::cd::Cd::run(y),
-
             ("", _) => Ok(()),
 
-            ("exit", _) => Err(CommandError::Exit),
-
             (x, y) => {
-                if '|' == *x.as_bytes().last().unwrap() as char {
-                    return builtins::cd::Cd::run(vec![x.to_string()]);
+                let raw_words = std::iter::once(x.to_string()).chain(y).collect();
+                let (raw_words, redirects) =
+                    split_redirects(raw_words).map_err(CommandError::Error)?;
+                let (assignments, mut words) = Self::expand_command(raw_words, shell)?;
+                Self::trace(&assignments, &words, shell);
+
+                // Assignments standing alone last for the rest of the session
+                if words.is_empty() {
+                    for (name, value) in assignments {
+                        check_env_value(&name, &value).map_err(CommandError::Error)?;
+                        env::set_var(name, value);
+                    }
+                    return Self::with_redirects(&redirects, shell, |_| Ok(()));
                 }
 
-                let args = y.into_iter().map(expand).collect::<Vec<_>>();
+                let keyword = words.remove(0);
+                Self::with_redirects(&redirects, shell, |shell| {
+                    Self::with_assignments(&assignments, || Self::dispatch(keyword, words, shell))
+                })
+            }
+        }
+    }
 
-                // Look for alias in keyword
-                // if found then run command again with keyword replaced with the alias
-                if let Some(alias) = &aliases.get(x) {
-                    let mut new_x = alias.to_string();
+    fn group(raw: &str) -> Option<&str> {
+        let inner = raw.trim().strip_prefix('{')?.strip_suffix('}')?;
+        if inner.starts_with(char::is_whitespace) {
+            Some(inner)
+        } else {
+            None
+        }
+    }
 
-                    // Add the arguments passed in as well
-                    for flags in &args {
-                        new_x.push_str(&format!(" {}", flags));
-                    }
+    // Expands a word; the one way that fails is an unbound variable under `set -u`, which
+    // ends a shell that is not interactive like in other shells
+    fn expand(word: &str, shell: &Shell) -> Result<Vec<String>, CommandError> {
+        expand_word(word, shell).map_err(|e| {
+            if shell.interactive {
+                CommandError::Error(e)
+            } else {
+                eprintln!("{}{}", shell.error_prefix(), e);
+                CommandError::Exit(1)
+            }
+        })
+    }
 
-                    Self::run(new_x, aliases)?;
+    // Splits leading `NAME=value` words off and expands everything else
+    #[allow(clippy::type_complexity)]
+    fn expand_command(
+        raw_words: Vec<String>,
+        shell: &Shell,
+    ) -> Result<(Vec<(String, String)>, Vec<String>), CommandError> {
+        let mut assignments = Vec::new();
+        for word in &raw_words {
+            match split_assignment(word) {
+                Some((name, value)) => {
+                    let value = Self::expand(value, shell)?;
+                    assignments.push((name.to_string(), value.concat()))
                 }
+                None => break,
+            }
+        }
+
+        let mut words = Vec::new();
+        for word in &raw_words[assignments.len()..] {
+            words.extend(Self::expand(word, shell)?);
+        }
+        Ok((assignments, words))
+    }
+
+    // `set -x` prints every command after expansion, behind the PS4 prefix
+    fn trace(assignments: &[(String, String)], words: &[String], shell: &Shell) {
+        if !shell.options.is_set("xtrace") {
+            return;
+        }
+        let prefix = env::var("PS4").unwrap_or_else(|_| String::from("+ "));
+        let line = assignments
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, quote_word(value)))
+            .chain(words.iter().map(|word| quote_word(word)))
+            .collect::<Vec<_>>();
+        // One write per line, stages of a pipeline trace at the same time
+        let line = format!("{}{}\n", prefix, line.join(" "));
+        io::stderr().write_all(line.as_bytes()).ok();
+    }
+
+    // Points the shell's own file descriptors at the redirection targets while `f` runs,
+    // so builtins and programs are redirected alike
+    fn with_redirects<T>(
+        redirects: &[Redirect],
+        shell: &mut Shell,
+        f: impl FnOnce(&mut Shell) -> Result<T, CommandError>,
+    ) -> Result<T, CommandError> {
+        if redirects.is_empty() {
+            return f(shell);
+        }
 
-                let stdin = Stdio::inherit();
-                let stdout = Stdio::inherit();
+        io::stdout().flush().ok();
+        let mut saved = Vec::new();
+        let result = Self::redirect(redirects, shell, &mut saved).and_then(|_| f(shell));
+        io::stdout().flush().ok();
 
-                // Execute the command and store its info as a Child
-                let mut child = Self::exec(x.to_string(), args, stdin, stdout)?;
-                Self::get_status(&mut child)
+        for (fd, copy) in saved.into_iter().rev() {
+            unsafe {
+                if copy == -1 {
+                    libc::close(fd);
+                } else {
+                    libc::dup2(copy, fd);
+                    libc::close(copy);
+                }
             }
         }
+        result
     }
 
-    pub fn run(x: String, y: &HashMap<&str, &str>) -> Result<(), CommandError> {
+    fn redirect(
+        redirects: &[Redirect],
+        shell: &Shell,
+        saved: &mut Vec<(i32, i32)>,
+    ) -> Result<(), CommandError> {
+        for redirect in redirects {
+            let target = match Self::expand(&redirect.target, shell)?.as_slice() {
+                [target] => target.to_string(),
+                _ => {
+                    return Err(CommandError::Error(format!(
+                        "{}: ambiguous redirect",
+                        redirect.target
+                    )))
+                }
+            };
+
+            // The copy to restore from is kept out of the way and is not passed on to programs
+            if !saved.iter().any(|(fd, _)| *fd == redirect.fd) {
+                let copy = unsafe { libc::fcntl(redirect.fd, libc::F_DUPFD_CLOEXEC, 10) };
+                saved.push((redirect.fd, copy));
+            }
+
+            let source = match redirect.kind {
+                RedirectKind::Duplicate => target
+                    .parse::<i32>()
+                    .map_err(|_| CommandError::Error(format!("{}: bad file descriptor", target)))?,
+                kind => Self::open_target(&target, kind, shell)?.into_raw_fd(),
+            };
+            if unsafe { libc::dup2(source, redirect.fd) } == -1 {
+                return Err(CommandError::Error(format!(
+                    "{}: {}",
+                    target,
+                    os_error_text(&io::Error::last_os_error())
+                )));
+            }
+            if redirect.kind != RedirectKind::Duplicate {
+                unsafe {
+                    libc::close(source);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn open_target(
+        target: &str,
+        kind: RedirectKind,
+        shell: &Shell,
+    ) -> Result<std::fs::File, CommandError> {
+        let mut options = OpenOptions::new();
+        match kind {
+            RedirectKind::Read => options.read(true),
+            RedirectKind::Append => options.append(true).create(true),
+            _ => options.write(true).create(true).truncate(true),
+        };
+
+        // Only regular files are protected, writing to /dev/null is still fine
+        if kind == RedirectKind::Write
+            && shell.options.is_set("noclobber")
+            && Path::new(target).is_file()
+        {
+            return Err(CommandError::Error(format!(
+                "{}: cannot overwrite existing file",
+                target
+            )));
+        }
+        options
+            .open(target)
+            .map_err(|e| CommandError::Error(format!("{}: {}", target, os_error_text(&e))))
+    }
+
+    // Assignments in front of a command only last for that command
+    fn with_assignments<T>(
+        assignments: &[(String, String)],
+        f: impl FnOnce() -> Result<T, CommandError>,
+    ) -> Result<T, CommandError> {
+        for (name, value) in assignments {
+            check_env_value(name, value).map_err(CommandError::Error)?;
+        }
+        let saved = assignments
+            .iter()
+            .map(|(name, _)| (name.to_string(), env::var(name).ok()))
+            .collect::<Vec<_>>();
+        for (name, value) in assignments {
+            env::set_var(name, value);
+        }
+
+        let result = f();
+
+        for (name, value) in saved {
+            match value {
+                Some(value) => env::set_var(name, value),
+                None => env::remove_var(name),
+            }
+        }
+        result
+    }
+
+    // `cmd &` starts a program without waiting for it and adds it to the job table
+    fn run_background(commands: Vec<String>, shell: &mut Shell) -> Result<(), CommandError> {
+        let raw = match commands.as_slice() {
+            [raw] if split_unquoted(raw, &["|"]).len() == 1 => raw.trim().to_string(),
+            _ => {
+                return Err(CommandError::Error(
+                    "only a single program can run in the background".to_string(),
+                ))
+            }
+        };
+
+        let (raw_words, redirects) =
+            split_redirects(split_words(&raw)).map_err(CommandError::Error)?;
+        let (assignments, mut words) = Self::expand_command(raw_words, shell)?;
+        Self::trace(&assignments, &words, shell);
+        if words.is_empty() {
+            return Ok(());
+        }
+
+        let keyword = words.remove(0);
+        if !matches!(shell.resolve(&keyword).first(), Some(Resolution::File(_))) {
+            return Err(CommandError::Error(format!(
+                "{}: only programs can run in the background",
+                keyword
+            )));
+        }
+
+        let child = Self::with_redirects(&redirects, shell, |shell| {
+            Self::with_assignments(&assignments, || {
+                Self::exec(&keyword, words, Stdio::null(), Stdio::inherit(), true)
+                    .map_err(|error| Self::spawn_error(&keyword, error, shell))
+            })
+        })?;
+        let job = shell.jobs.add(child, raw);
+        println!("[{}] {}", job.id, job.pid());
+        shell.last_background = Some(job.pid());
+        Ok(())
+    }
+
+    fn dispatch(keyword: String, args: Vec<String>, shell: &mut Shell) -> Result<(), CommandError> {
+        if let Some(body) = shell.functions.get(&keyword).cloned() {
+            return Self::call_function(body, args, shell);
+        }
+
+        if let Some(builtin) = builtins::lookup(&keyword) {
+            return builtin(shell, args);
+        }
+
+        // With autocd a directory typed as a command is entered, unless a program has its name
+        if shell.options.is_set("autocd")
+            && args.is_empty()
+            && find_in_path(&keyword).is_empty()
+            && Path::new(&keyword).is_dir()
+        {
+            return builtins::cd::Cd::run(shell, vec![keyword]);
+        }
+
+        Self::spawn(keyword, args, shell)
+    }
+
+    pub fn call_function(
+        body: String,
+        args: Vec<String>,
+        shell: &mut Shell,
+    ) -> Result<(), CommandError> {
+        if shell.depth >= MAX_DEPTH {
+            return Err(CommandError::Error(
+                "maximum function nesting level exceeded".to_string(),
+            ));
+        }
+        let saved = std::mem::replace(&mut shell.positional, args);
+        shell.depth += 1;
+        let result = Self::run(body, shell);
+        shell.depth -= 1;
+        shell.positional = saved;
+        catch_return(result)
+    }
+
+    pub fn run(x: String, shell: &mut Shell) -> Result<(), CommandError> {
         let mut last_return = Ok(());
-        for com in x.split(';') {
-            last_return = Self::run_linked_commands(com.into(), y);
+        let x = expand_aliases(&strip_comments(&x), &shell.aliases);
+        if shell.debug_ast {
+            if let Ok(ast) = parse_ast(&x) {
+                eprintln!("{:#?}", ast);
+            }
+        }
+        let items = split_list(&x);
+        let last = items.len().saturating_sub(1);
+        for (i, item) in items.into_iter().enumerate() {
+            last_return = if item.background {
+                Self::run_background(item.commands, shell)
+            } else {
+                Self::run_linked_commands(item.commands, shell)
+            };
+            match last_return {
+                Err(CommandError::Exit(_) | CommandError::Return(_)) => break,
+                // Only the error of the last item is left for the caller to report
+                Err(CommandError::Error(ref e)) if i != last => {
+                    eprintln!("{}{}", shell.error_prefix(), e)
+                }
+                _ => (),
+            }
         }
         last_return
     }
 
-    fn call_eval(raw: String, x: &HashMap<&str, &str>) -> Result<(), CommandError> {
-        Self::eval(raw, x)
+    // Runs a whole script in the current shell, `name` is used to point at failing lines
+    pub fn run_script(script: &str, name: &str, shell: &mut Shell) -> Result<(), CommandError> {
+        let (commands, unfinished) = complete_commands(script);
+        let commands = commands
+            .into_iter()
+            .map(|(line, command)| (line, Ok(command)))
+            .chain(unfinished.map(|(line, e)| (line, Err(e))));
+
+        let saved = shell.location.take();
+        let mut last_return = Ok(());
+        for (line, command) in commands {
+            shell.location = Some(format!("{}:{}", name, line));
+            let result = match command {
+                Ok(command) => Self::run(command, shell),
+                Err(e) => Err(CommandError::Error(format!("syntax error: {}", e))),
+            };
+            last_return = match result {
+                Err(CommandError::Error(e)) => {
+                    eprintln!("{}{}", shell.error_prefix(), e);
+                    Err(CommandError::Finished(1))
+                }
+                Err(e @ (CommandError::Exit(_) | CommandError::Return(_))) => {
+                    last_return = Err(e);
+                    break;
+                }
+                other => other,
+            };
+        }
+        shell.location = saved;
+        last_return
+    }
+
+    fn call_eval(raw: String, shell: &mut Shell) -> Result<(), CommandError> {
+        let result = match Self::time_keyword(&raw) {
+            Some((format, rest)) => Self::time(rest.to_string(), format, shell),
+            None => Self::eval_pipeline(raw, shell),
+        };
+        shell.last_status = status_of(&result);
+        result
+    }
+
+    // `time [-p] pipeline`, returns the format to report with and the pipeline
+    fn time_keyword(raw: &str) -> Option<(String, &str)> {
+        fn keyword<'a>(raw: &'a str, word: &str) -> Option<&'a str> {
+            let rest = raw.trim_start().strip_prefix(word)?;
+            if rest.is_empty() || rest.starts_with(char::is_whitespace) {
+                Some(rest)
+            } else {
+                None
+            }
+        }
+
+        let rest = keyword(raw, "time")?;
+        match keyword(rest, "-p") {
+            Some(rest) => Some((POSIX_TIMEFORMAT.to_string(), rest)),
+            None => {
+                let format = env::var("TIMEFORMAT").unwrap_or_else(|_| DEFAULT_TIMEFORMAT.into());
+                Some((format, rest))
+            }
+        }
+    }
+
+    // Reports the wall clock time of a pipeline and the CPU time vsh and its children spent on it
+    fn time(raw: String, format: String, shell: &mut Shell) -> Result<(), CommandError> {
+        let start = Instant::now();
+        let (user, system) = cpu_time(libc::RUSAGE_SELF);
+        let (child_user, child_system) = shell.child_usage;
+
+        let result = Self::eval_pipeline(raw, shell);
+
+        let real = start.elapsed();
+        let (user_after, system_after) = cpu_time(libc::RUSAGE_SELF);
+        let user = user_after - user + shell.child_usage.0 - child_user;
+        let system = system_after - system + shell.child_usage.1 - child_system;
+        if !format.is_empty() {
+            eprintln!("{}", format_time(&format, real, user, system));
+        }
+        result
+    }
+
+    fn eval_pipeline(raw: String, shell: &mut Shell) -> Result<(), CommandError> {
+        let stages = split_unquoted(&raw, &["|"])
+            .into_iter()
+            .map(|(stage, _)| stage)
+            .collect::<Vec<_>>();
+        if stages.len() == 1 {
+            return Self::eval(raw, shell);
+        }
+        if stages.iter().any(|stage| stage.trim().is_empty()) {
+            return Err(CommandError::Error(
+                "syntax error near unexpected token `|'".to_string(),
+            ));
+        }
+        Self::run_pipeline(stages, shell)
+    }
+
+    // Every stage of `a | b | c` runs in a forked copy of the shell, so builtins, functions
+    // and groups can be part of a pipeline too; its status is the one of the last stage
+    fn run_pipeline(stages: Vec<String>, shell: &mut Shell) -> Result<(), CommandError> {
+        let pipe_error = || CommandError::Error(format!("pipe: {}", io::Error::last_os_error()));
+        // Anything still buffered would otherwise be printed by every fork
+        io::stdout().flush().ok();
+
+        let last = stages.len() - 1;
+        let mut pids = Vec::new();
+        let mut input = None;
+        for (i, stage) in stages.into_iter().enumerate() {
+            let mut fds = [0; 2];
+            if i < last && unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+                return Err(pipe_error());
+            }
+
+            match unsafe { libc::fork() } {
+                -1 => return Err(pipe_error()),
+                0 => unsafe {
+                    if let Some(fd) = input {
+                        libc::dup2(fd, 0);
+                        libc::close(fd);
+                    }
+                    if i < last {
+                        libc::dup2(fds[1], 1);
+                        libc::close(fds[0]);
+                        libc::close(fds[1]);
+                    }
+                    Self::subshell(stage, shell)
+                },
+                pid => {
+                    pids.push(pid);
+                    unsafe {
+                        if let Some(fd) = input {
+                            libc::close(fd);
+                        }
+                        if i < last {
+                            libc::close(fds[1]);
+                        }
+                    }
+                    input = if i < last { Some(fds[0]) } else { None };
+                }
+            }
+        }
+
+        // With pipefail the last stage that failed decides the status
+        let pipefail = shell.options.is_set("pipefail");
+        let mut result = Ok(());
+        for pid in pids {
+            let stage = match Self::wait_pid(pid, shell) {
+                Ok(status) => Self::exit_result(status),
+                Err(e) => Err(CommandError::Error(format!("pipe: {}", e))),
+            };
+            if !pipefail || stage.is_err() {
+                result = stage;
+            }
+        }
+        result
     }
 
-    fn run_linked_commands(commands: String, x: &HashMap<&str, &str>) -> Result<(), CommandError> {
-        for linked_com in commands.split("&&") {
-            if let Err(e) = Self::call_eval(linked_com.to_string(), x) {
-                return Err(e);
+    // Runs one stage of a pipeline in a fork and exits with its status
+    fn subshell(raw: String, shell: &mut Shell) -> ! {
+        // Traps are not inherited, ignored signals stay ignored
+        shell.traps.retain(|_, command| command.is_empty());
+        // Only the forking thread lives on in the child, so the handlers that fed the signal
+        // thread give way to the default actions, SIGPIPE included like for any program, and
+        // the queue to a fresh one whose locks that thread cannot be holding
+        for &signal in TRAPPABLE.iter().chain(&[libc::SIGPIPE]) {
+            let ignored = signal_name(signal).is_some_and(|name| shell.traps.contains_key(name));
+            let action = if ignored {
+                libc::SIG_IGN
+            } else {
+                libc::SIG_DFL
+            };
+            unsafe { libc::signal(signal, action) };
+        }
+        shell.signals = SignalQueue::default();
+        let result = Self::eval(raw, shell);
+        if let Err(CommandError::Error(e)) = &result {
+            eprintln!("vsh: {}", e);
+        }
+        io::stdout().flush().ok();
+        unsafe { libc::_exit(status_of(&result)) }
+    }
+
+    fn run_linked_commands(linked: Vec<String>, shell: &mut Shell) -> Result<(), CommandError> {
+        let last = linked.len() - 1;
+        for (i, linked_com) in linked.into_iter().enumerate() {
+            let result = Self::call_eval(linked_com, shell);
+            Self::run_pending_traps(shell)?;
+            match result {
+                Err(CommandError::Exit(_) | CommandError::Return(_)) => return result,
+                // Like other shells, only the last command of a `&&` chain can trigger ERR
+                // or errexit, and ERR only at the top level
+                Err(e) if i == last => {
+                    if shell.depth == 0 {
+                        Self::run_trap("ERR", shell)?;
+                    }
+                    if shell.options.is_set("errexit") && !shell.in_trap {
+                        return Err(CommandError::Exit(e.status()));
+                    }
+                    return Err(e);
+                }
+                Err(_) => return result,
+                Ok(()) => (),
             }
         }
         Ok(())
     }
 
-    fn exec(
+    // Runs the command `trap` set for `name`, `$?` is left as it was
+    pub fn run_trap(name: &str, shell: &mut Shell) -> Result<(), CommandError> {
+        let command = match shell.traps.get(name) {
+            Some(command) if !command.is_empty() && !shell.in_trap => command.to_string(),
+            _ => return Ok(()),
+        };
+
+        let status = shell.last_status;
+        shell.in_trap = true;
+        let result = Self::run(command, shell);
+        shell.in_trap = false;
+        shell.last_status = status;
+
+        match result {
+            Err(CommandError::Exit(code)) => Err(CommandError::Exit(code)),
+            Err(CommandError::Error(e)) => {
+                eprintln!("vsh: trap {}: {}", name, e);
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Signals are only queued by the signal thread, their traps run here between commands
+    pub fn run_pending_traps(shell: &mut Shell) -> Result<(), CommandError> {
+        if shell.in_trap {
+            return Ok(());
+        }
+        for signal in shell.signals.take() {
+            match signal_name(signal) {
+                Some(name) if shell.traps.contains_key(name) => Self::run_trap(name, shell)?,
+                // An untrapped SIGHUP is queued as well, to leave through the usual exit
+                _ if signal == libc::SIGHUP => return Err(CommandError::Exit(128 + libc::SIGHUP)),
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    // Run a program from PATH in the foreground and wait for it
+    pub fn spawn(
         keyword: String,
         args: Vec<String>,
-        stdout: Stdio,
+        shell: &mut Shell,
+    ) -> Result<(), CommandError> {
+        let stdin = Stdio::inherit();
+        let stdout = Stdio::inherit();
+
+        // Execute the command and store its info as a Child
+        match Self::exec(&keyword, args.clone(), stdin, stdout, false) {
+            Ok(child) => Self::get_status(&child, shell),
+            Err(error) if error.kind() == io::ErrorKind::NotFound && !keyword.contains('/') => {
+                Self::command_not_found(keyword, args, shell)
+            }
+            Err(error) => Err(Self::spawn_error(&keyword, error, shell)),
+        }
+    }
+
+    // Hands a missing command to the `command_not_found_handler` function or the configured
+    // handler, otherwise suggests commands with similar names
+    fn command_not_found(
+        keyword: String,
+        args: Vec<String>,
+        shell: &mut Shell,
+    ) -> Result<(), CommandError> {
+        const HANDLER: &str = "command_not_found_handler";
+        let words = std::iter::once(keyword.clone())
+            .chain(args)
+            .collect::<Vec<_>>();
+
+        // Handlers are taken out while they run, so a missing command inside one cannot loop
+        if shell.command_not_found.use_function.unwrap_or(true) {
+            if let Some(body) = shell.functions.remove(HANDLER) {
+                let result = Self::call_function(body.clone(), words, shell);
+                shell.functions.entry(HANDLER.to_string()).or_insert(body);
+                return result;
+            }
+        }
+        if let Some(handler) = shell.command_not_found.handler.take() {
+            let command = std::iter::once(handler.clone())
+                .chain(words.iter().map(|word| quote_word(word)))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let result = Self::run(command, shell);
+            shell.command_not_found.handler = Some(handler);
+            return result;
+        }
+
+        eprintln!("{}{}: command not found", shell.error_prefix(), keyword);
+        if shell.command_not_found.suggest.unwrap_or(true) {
+            let candidates = builtins::names()
+                .into_iter()
+                .map(str::to_string)
+                .chain(shell.aliases.keys().cloned())
+                .chain(shell.functions.keys().cloned())
+                .chain(path_executables());
+            let found = closest(&keyword, candidates);
+            if !found.is_empty() {
+                let found = found
+                    .iter()
+                    .map(|name| format!("`{}`", name))
+                    .collect::<Vec<_>>();
+                eprintln!("vsh: did you mean {}?", found.join(", "));
+            }
+        }
+        Err(CommandError::Finished(127))
+    }
+
+    // Background jobs get a process group of their own, so Ctrl+C at the prompt spares them
+    fn exec(
+        keyword: &str,
+        args: Vec<String>,
         stdin: Stdio,
-    ) -> Result<Child, CommandError> {
-        match Command::new(&keyword)
-            .args(args)
-            .stdin(stdin)
-            .stdout(stdout)
-            .spawn()
-        {
-            Ok(ok) => Ok(ok),
-            Err(_) => Err(CommandError::Error(format!(
-                "No such command as `{keyword}`"
-            ))),
+        stdout: Stdio,
+        background: bool,
+    ) -> io::Result<Child> {
+        let mut command = Command::new(keyword);
+        command.args(args).stdin(stdin).stdout(stdout);
+        if background {
+            command.process_group(0);
         }
+        command.spawn()
     }
 
-    fn get_status(child: &mut Child) -> Result<(), CommandError> {
-        match child.wait() {
-            Ok(status) => match status.code() {
-                Some(code) => {
-                    if code > 0 {
-                        Err(CommandError::Finished(code))
-                    } else {
-                        Ok(())
-                    }
-                }
-                None => Err(CommandError::Terminated(127)),
-            },
-            Err(_) => Err(CommandError::Error(
-                "Command could not be executed".to_string(),
-            )),
+    // Like other shells: 127 when the command is not there, 126 when it cannot be run
+    fn spawn_error(keyword: &str, error: io::Error, shell: &Shell) -> CommandError {
+        let (status, text) = match error.kind() {
+            io::ErrorKind::NotFound if !keyword.contains('/') => {
+                (127, String::from("command not found"))
+            }
+            io::ErrorKind::NotFound => (127, os_error_text(&error)),
+            _ if Path::new(keyword).is_dir() => (126, String::from("Is a directory")),
+            _ => (126, os_error_text(&error)),
+        };
+        eprintln!("{}{}: {}", shell.error_prefix(), keyword, text);
+        CommandError::Finished(status)
+    }
+
+    fn get_status(child: &Child, shell: &mut Shell) -> Result<(), CommandError> {
+        match Self::wait_pid(child.id() as libc::pid_t, shell) {
+            Ok(status) => Self::exit_result(status),
+            Err(e) => Err(CommandError::Error(os_error_text(&e))),
+        }
+    }
+
+    // Says why a foreground program died, unless it was Ctrl+C or a closed pipe
+    fn report_signal(status: ExitStatus) {
+        let signal = match status.signal() {
+            Some(signal) if signal != libc::SIGINT && signal != libc::SIGPIPE => signal,
+            _ => return,
+        };
+        let text = unsafe { CStr::from_ptr(libc::strsignal(signal)) }.to_string_lossy();
+        if status.core_dumped() {
+            eprintln!("{} (core dumped)", text);
+        } else {
+            eprintln!("{}", text);
+        }
+    }
+
+    // Reaps a foreground child with wait4, so its CPU time can be added up for `time`
+    fn wait_pid(pid: libc::pid_t, shell: &mut Shell) -> io::Result<ExitStatus> {
+        let mut status = 0;
+        let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+        while unsafe { libc::wait4(pid, &mut status, 0, &mut usage) } == -1 {
+            let error = io::Error::last_os_error();
+            if error.kind() != io::ErrorKind::Interrupted {
+                return Err(error);
+            }
+        }
+        shell.child_usage.0 += timeval(usage.ru_utime);
+        shell.child_usage.1 += timeval(usage.ru_stime);
+
+        let status = ExitStatus::from_raw(status);
+        shell.core_dumped = status.core_dumped();
+        Self::report_signal(status);
+        Ok(status)
+    }
+
+    pub fn exit_result(status: ExitStatus) -> Result<(), CommandError> {
+        match (status.code(), status.signal()) {
+            (Some(0), _) => Ok(()),
+            (Some(code), _) => Err(CommandError::Finished(code)),
+            // Deaths by signal are reported as 128 + the signal number
+            (None, Some(signal)) => Err(CommandError::Terminated(128 + signal)),
+            (None, None) => Err(CommandError::Terminated(128)),
         }
     }
 }
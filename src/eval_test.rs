@@ -0,0 +1,108 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::io;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::ExitStatus;
+
+    use crate::eval::*;
+    use crate::shell::Shell;
+    use crate::utils::os_error_text;
+
+    #[test]
+    fn exit_result_test() {
+        // Raw wait statuses: the exit code sits in the second byte, a signal in the low bits
+        assert_eq!(
+            status_of(&Vshcommand::exit_result(ExitStatus::from_raw(0))),
+            0
+        );
+        assert_eq!(
+            status_of(&Vshcommand::exit_result(ExitStatus::from_raw(3 << 8))),
+            3
+        );
+        assert!(matches!(
+            Vshcommand::exit_result(ExitStatus::from_raw(libc::SIGSEGV)),
+            Err(CommandError::Terminated(139))
+        ));
+        assert!(matches!(
+            Vshcommand::exit_result(ExitStatus::from_raw(libc::SIGKILL)),
+            Err(CommandError::Terminated(137))
+        ));
+        assert!(ExitStatus::from_raw(libc::SIGSEGV | 0x80).core_dumped());
+    }
+
+    #[test]
+    fn os_error_text_test() {
+        let error = io::Error::from_raw_os_error(libc::EACCES);
+        assert_eq!(os_error_text(&error), "Permission denied");
+        let error = io::Error::other("plain");
+        assert_eq!(os_error_text(&error), "plain");
+    }
+
+    #[test]
+    fn nounset_test() {
+        let line = "set -u; unset $VSH_SURELY_UNSET; exit 3";
+        let mut shell = Shell::new(HashMap::new());
+        assert!(matches!(
+            Vshcommand::run(line.to_string(), &mut shell),
+            Err(CommandError::Exit(1))
+        ));
+        // An interactive shell reports it and goes on with the next command
+        let mut shell = Shell::new(HashMap::new());
+        shell.interactive = true;
+        assert!(matches!(
+            Vshcommand::run(line.to_string(), &mut shell),
+            Err(CommandError::Exit(3))
+        ));
+    }
+
+    #[test]
+    fn run_script_test() {
+        let mut shell = Shell::new(HashMap::new());
+        let script = "unset a=b\nvsh-surely-not-a-command\necho 'open\n";
+        assert!(matches!(
+            Vshcommand::run_script(script, "script.vsh", &mut shell),
+            Err(CommandError::Finished(1))
+        ));
+        assert_eq!(shell.location, None);
+    }
+
+    #[test]
+    fn function_nesting_test() {
+        // Debug builds need more stack than a test thread has to reach the limit
+        let result = std::thread::Builder::new()
+            .stack_size(256 << 20)
+            .spawn(|| {
+                let mut shell = Shell::new(HashMap::new());
+                Vshcommand::run("g() { g; }; g".to_string(), &mut shell)
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+        assert!(matches!(
+            result,
+            Err(CommandError::Error(e)) if e == "maximum function nesting level exceeded"
+        ));
+    }
+
+    #[test]
+    fn nul_assignment_test() {
+        let mut shell = Shell::new(HashMap::new());
+        for line in [
+            "VSH_NUL_TEST=a\0b",
+            "VSH_NUL_TEST=a\0b unset x",
+            "export VSH_NUL_TEST=a\0b",
+        ] {
+            assert!(matches!(
+                Vshcommand::run(line.to_string(), &mut shell),
+                Err(CommandError::Error(e)) if e.ends_with("VSH_NUL_TEST: cannot hold a NUL byte")
+            ));
+        }
+        assert!(std::env::var("VSH_NUL_TEST").is_err());
+    }
+}
@@ -0,0 +1,154 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::HashMap;
+use std::process::Child;
+
+use crate::eval::{status_of, Vshcommand};
+
+pub struct Job {
+    pub id: usize,
+    pub child: Child,
+    pub command: String,
+    // Set once the job has finished, holds what `$?` would be
+    pub status: Option<i32>,
+    // `disown -h` keeps the job in the table but spares it the SIGHUP on exit
+    pub nohup: bool,
+}
+
+impl Job {
+    pub fn pid(&self) -> u32 {
+        self.child.id()
+    }
+
+    fn poll(&mut self) -> Option<i32> {
+        if self.status.is_none() {
+            if let Ok(Some(status)) = self.child.try_wait() {
+                self.status = Some(status_of(&Vshcommand::exit_result(status)));
+            }
+        }
+        self.status
+    }
+
+    pub fn state(&self) -> String {
+        match self.status {
+            None => String::from("Running"),
+            Some(0) => String::from("Done"),
+            Some(code) => format!("Exit {}", code),
+        }
+    }
+}
+
+// Background jobs started with `&`, in the order they were started
+#[derive(Default)]
+pub struct Jobs {
+    list: Vec<Job>,
+    // Statuses of reaped jobs by PID, so `wait $!` still works after a job was reported
+    finished: HashMap<u32, i32>,
+}
+
+impl Jobs {
+    pub fn add(&mut self, child: Child, command: String) -> &Job {
+        let id = self.list.last().map_or(1, |job| job.id + 1);
+        self.list.push(Job {
+            id,
+            child,
+            command,
+            status: None,
+            nohup: false,
+        });
+        self.list.last().unwrap()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Job> {
+        self.list.iter()
+    }
+
+    // `+` for the most recent job and `-` for the one before it, as printed by `jobs`
+    pub fn marker(&self, id: usize) -> char {
+        let len = self.list.len();
+        match self.list.iter().position(|job| job.id == id) {
+            Some(i) if i + 1 == len => '+',
+            Some(i) if i + 2 == len => '-',
+            _ => ' ',
+        }
+    }
+
+    // Resolves `%1`, `%+`, `%%`, `%-`, `%name`, `%?text` or a plain PID to a job id
+    pub fn find(&self, spec: &str) -> Option<usize> {
+        let job = match spec.strip_prefix('%') {
+            Some("") | Some("+") | Some("%") => self.list.last(),
+            Some("-") => self.list.iter().rev().nth(1),
+            Some(text) => match text.parse::<usize>() {
+                Ok(id) => self.list.iter().find(|job| job.id == id),
+                Err(_) => match text.strip_prefix('?') {
+                    Some(text) => self.list.iter().rev().find(|j| j.command.contains(text)),
+                    None => self.list.iter().rev().find(|j| j.command.starts_with(text)),
+                },
+            },
+            None => {
+                let pid = spec.parse::<u32>().ok()?;
+                self.list.iter().find(|job| job.pid() == pid)
+            }
+        };
+        job.map(|job| job.id)
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Job> {
+        self.list.iter().find(|job| job.id == id)
+    }
+
+    pub fn get_mut(&mut self, id: usize) -> Option<&mut Job> {
+        self.list.iter_mut().find(|job| job.id == id)
+    }
+
+    pub fn remove(&mut self, id: usize) -> Option<Job> {
+        let i = self.list.iter().position(|job| job.id == id)?;
+        Some(self.list.remove(i))
+    }
+
+    // Blocks until the job is done and drops it from the table
+    pub fn wait(&mut self, id: usize) -> Option<i32> {
+        let mut job = self.remove(id)?;
+        if let Some(status) = job.status {
+            return Some(status);
+        }
+        match job.child.wait() {
+            Ok(status) => Some(status_of(&Vshcommand::exit_result(status))),
+            Err(_) => Some(127),
+        }
+    }
+
+    // Takes every job that finished since the last call out of the table
+    pub fn reap(&mut self) -> Vec<Job> {
+        for job in self.list.iter_mut() {
+            job.poll();
+        }
+        let (done, running) = std::mem::take(&mut self.list)
+            .into_iter()
+            .partition(|job| job.status.is_some());
+        self.list = running;
+        for job in &done {
+            self.finished.insert(job.pid(), job.status.unwrap());
+        }
+        done
+    }
+
+    pub fn take_finished(&mut self, pid: u32) -> Option<i32> {
+        self.finished.remove(&pid)
+    }
+
+    // Sent when vsh exits, jobs removed with `disown` or marked with `disown -h` are left alone
+    pub fn hangup(&mut self) {
+        for job in self.list.iter_mut() {
+            if !job.nohup && job.poll().is_none() {
+                unsafe {
+                    libc::kill(-(job.pid() as libc::pid_t), libc::SIGHUP);
+                }
+            }
+        }
+    }
+}
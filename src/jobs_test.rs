@@ -0,0 +1,37 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use crate::jobs::*;
+
+    #[test]
+    fn job_spec_test() {
+        let mut jobs = Jobs::default();
+        let first = Command::new("sleep").arg("5").spawn().unwrap();
+        let second = Command::new("true").spawn().unwrap();
+        let pid = first.id();
+        jobs.add(first, "sleep 5".to_string());
+        jobs.add(second, "true".to_string());
+
+        assert_eq!(jobs.find("%1"), Some(1));
+        assert_eq!(jobs.find("%+"), Some(2));
+        assert_eq!(jobs.find("%%"), Some(2));
+        assert_eq!(jobs.find("%-"), Some(1));
+        assert_eq!(jobs.find("%sle"), Some(1));
+        assert_eq!(jobs.find("%?rue"), Some(2));
+        assert_eq!(jobs.find(&pid.to_string()), Some(1));
+        assert_eq!(jobs.find("%3"), None);
+        assert_eq!(jobs.marker(1), '-');
+        assert_eq!(jobs.marker(2), '+');
+
+        assert_eq!(jobs.wait(2), Some(0));
+        jobs.get_mut(1).unwrap().child.kill().unwrap();
+        assert!(jobs.wait(1).is_some());
+        assert_eq!(jobs.find("%1"), None);
+    }
+}
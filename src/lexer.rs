@@ -0,0 +1,593 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::HashMap;
+
+use crate::shell::Shell;
+use crate::utils::{glob, has_glob, home_dir};
+
+// Keeps track of quotes, escapes and `{ }` groups while walking over a command line
+#[derive(Default)]
+struct Scan {
+    quote: Option<char>,
+    depth: usize,
+    escaped: bool,
+}
+
+impl Scan {
+    // Feeds one character, returns true if it has its special meaning at the top level
+    fn step(&mut self, c: char) -> bool {
+        if self.escaped {
+            self.escaped = false;
+            return false;
+        }
+        if c == '\\' && self.quote != Some('\'') {
+            self.escaped = true;
+            return false;
+        }
+        match self.quote {
+            Some(q) => {
+                if c == q {
+                    self.quote = None;
+                }
+                false
+            }
+            None => match c {
+                '\'' | '"' => {
+                    self.quote = Some(c);
+                    false
+                }
+                '{' => {
+                    self.depth += 1;
+                    false
+                }
+                '}' => {
+                    self.depth = self.depth.saturating_sub(1);
+                    false
+                }
+                _ => self.depth == 0,
+            },
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.quote.is_none() && self.depth == 0 && !self.escaped
+    }
+}
+
+// Splits on any of `separators` as long as it is not quoted, escaped or inside `{ }`
+// Each piece comes with the separator that ended it
+// Longer separators have to come first, e.g. `&&` before `&`
+// The `|` and `&` of redirections like `>|`, `2>&1` and `&>` never separate
+pub fn split_unquoted<'a>(input: &str, separators: &[&'a str]) -> Vec<(String, Option<&'a str>)> {
+    let mut pieces = Vec::new();
+    let mut current = String::new();
+    let mut scan = Scan::default();
+
+    let mut i = 0;
+    while i < input.len() {
+        let rest = &input[i..];
+        let c = rest.chars().next().unwrap();
+        let redirection = current.ends_with(['<', '>']) || rest.starts_with("&>");
+        if scan.step(c) && !redirection {
+            if let Some(sep) = separators.iter().find(|sep| rest.starts_with(**sep)) {
+                pieces.push((std::mem::take(&mut current), Some(*sep)));
+                i += sep.len();
+                continue;
+            }
+        }
+        current.push(c);
+        i += c.len_utf8();
+    }
+    pieces.push((current, None));
+    pieces
+}
+
+// One entry of a command list: commands chained with `&&`, run in the background if it ended with `&`
+#[derive(Debug, PartialEq)]
+pub struct ListItem {
+    pub commands: Vec<String>,
+    pub background: bool,
+}
+
+// Splits a command line on `;`, newlines, `&` and `&&`
+pub fn split_list(input: &str) -> Vec<ListItem> {
+    let mut items = Vec::new();
+    let mut commands = Vec::new();
+
+    for (command, separator) in split_unquoted(input, &["&&", ";", "\n", "&"]) {
+        commands.push(command);
+        if separator == Some("&&") {
+            continue;
+        }
+
+        let item = ListItem {
+            commands: std::mem::take(&mut commands),
+            background: separator == Some("&"),
+        };
+        if item
+            .commands
+            .iter()
+            .any(|command| !command.trim().is_empty())
+        {
+            items.push(item);
+        }
+    }
+    items
+}
+
+// Drops everything from an unquoted `#` that starts a word up to the end of its line
+pub fn strip_comments(input: &str) -> String {
+    let mut out = String::new();
+    let mut scan = Scan::default();
+    let mut in_comment = false;
+    let mut prev = ' ';
+
+    for c in input.chars() {
+        // Comments are dropped inside function bodies as well
+        scan.depth = 0;
+        if in_comment {
+            if c == '\n' {
+                in_comment = false;
+                out.push(c);
+            }
+            continue;
+        }
+        if scan.step(c) && c == '#' && prev.is_whitespace() {
+            in_comment = true;
+            continue;
+        }
+        out.push(c);
+        prev = c;
+    }
+    out
+}
+
+// Splits a command into words on unquoted whitespace, quotes are kept for `expand_word`
+pub fn split_words(input: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut scan = Scan::default();
+
+    for c in input.chars() {
+        let active = scan.step(c);
+        // Braces only group commands, they do not glue words together
+        scan.depth = 0;
+        if active && c.is_whitespace() {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+        } else {
+            current.push(c);
+        }
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+// Groups the lines of a script into complete commands, i.e. with every quote and brace closed
+// Each command comes with the line it starts on; a command the script ends in the middle of
+// comes back as the line it starts on and what it is missing
+#[allow(clippy::type_complexity)]
+pub fn complete_commands(script: &str) -> (Vec<(usize, String)>, Option<(usize, String)>) {
+    let mut commands = Vec::new();
+    let mut current = String::new();
+    let mut start = 0;
+    let mut scan = Scan::default();
+
+    for (number, line) in script.lines().enumerate() {
+        if current.is_empty() {
+            start = number + 1;
+        }
+        for c in line.chars() {
+            scan.step(c);
+        }
+
+        if scan.escaped {
+            // A trailing backslash joins the next line onto this one
+            current.push_str(&line[..line.len() - 1]);
+            scan.escaped = false;
+            continue;
+        }
+        current.push_str(line);
+
+        if scan.is_complete() {
+            commands.push((start, std::mem::take(&mut current)));
+        } else {
+            current.push('\n');
+            scan.step('\n');
+        }
+    }
+    let missing = match (scan.quote, scan.depth) {
+        (Some(quote), _) => Some(quote),
+        (None, 0) => None,
+        (None, _) => Some('}'),
+    };
+    let unfinished = match missing {
+        Some(missing) => Some((
+            start,
+            format!(
+                "unexpected end of file looking for the closing `{}`",
+                missing
+            ),
+        )),
+        None => {
+            if !current.trim().is_empty() {
+                commands.push((start, current));
+            }
+            None
+        }
+    };
+    (commands, unfinished)
+}
+
+// Puts the arguments of an aliased command where the alias value has `$1` to `$9` or `$@`,
+// arguments no placeholder used are added at the end; single quoted text is left alone
+fn substitute_args(value: &str, args: &[String]) -> Option<String> {
+    let chars = value.chars().collect::<Vec<_>>();
+    let mut out = String::new();
+    let mut used = vec![false; args.len()];
+    let mut placeholders = false;
+    let mut in_single = false;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match (c, chars.get(i + 1)) {
+            ('\'', _) => in_single = !in_single,
+            ('$', Some('@')) if !in_single => {
+                placeholders = true;
+                used.iter_mut().for_each(|used| *used = true);
+                out.push_str(&args.join(" "));
+                i += 2;
+                continue;
+            }
+            ('$', Some(digit @ '1'..='9')) if !in_single => {
+                placeholders = true;
+                let n = digit.to_digit(10).unwrap() as usize - 1;
+                if let Some(arg) = args.get(n) {
+                    used[n] = true;
+                    out.push_str(arg);
+                }
+                i += 2;
+                continue;
+            }
+            _ => (),
+        }
+        out.push(c);
+        i += 1;
+    }
+
+    if !placeholders {
+        return None;
+    }
+    for (arg, used) in args.iter().zip(used) {
+        if !used {
+            out.push(' ');
+            out.push_str(arg);
+        }
+    }
+    Some(out)
+}
+
+// Expands the alias in the first word of a single command, `skip` holds the aliases
+// being expanded already so an alias never expands inside itself
+fn expand_command_alias(command: &str, aliases: &HashMap<String, String>, skip: &[&str]) -> String {
+    let start = command.len() - command.trim_start().len();
+    let end = command[start..]
+        .find(char::is_whitespace)
+        .map_or(command.len(), |i| start + i);
+    let (word, rest) = (&command[start..end], &command[end..]);
+
+    // Whatever follows `time` is a command of its own
+    if word == "time" {
+        return format!(
+            "{}{}",
+            &command[..end],
+            expand_command_alias(rest, aliases, skip)
+        );
+    }
+    let value = match aliases.get(word) {
+        Some(value) if !skip.contains(&word) => value,
+        _ => return command.to_string(),
+    };
+
+    let mut skip = skip.to_vec();
+    skip.push(word);
+    let expanded = match substitute_args(value, &split_words(rest)) {
+        Some(substituted) => expand_aliases_except(&substituted, aliases, &skip),
+        // A value ending in a space makes the next word a candidate for expansion as well
+        None if value.ends_with(' ') => format!(
+            "{}{}",
+            expand_aliases_except(value.trim_end(), aliases, &skip),
+            expand_command_alias(rest, aliases, &skip)
+        ),
+        None => format!("{}{}", expand_aliases_except(value, aliases, &skip), rest),
+    };
+    format!("{}{}", &command[..start], expanded)
+}
+
+fn expand_aliases_except(input: &str, aliases: &HashMap<String, String>, skip: &[&str]) -> String {
+    let mut out = String::new();
+    for (command, separator) in split_unquoted(input, &["&&", ";", "\n", "&", "|"]) {
+        out.push_str(&expand_command_alias(&command, aliases, skip));
+        out.push_str(separator.unwrap_or_default());
+    }
+    out
+}
+
+// Replaces the first word of every command in a command line with its alias, once
+// Quoted or escaped words are never aliases, so `\ls` runs the real `ls`
+pub fn expand_aliases(input: &str, aliases: &HashMap<String, String>) -> String {
+    expand_aliases_except(input, aliases, &[])
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+// Reads the variable name after a `$`, returns the name and how many characters it used
+fn read_var_name(chars: &[char]) -> Option<(String, usize)> {
+    match chars.first() {
+        Some('{') => {
+            let end = chars.iter().position(|c| *c == '}')?;
+            Some((chars[1..end].iter().collect(), end + 1))
+        }
+        Some(c) if c.is_ascii_digit() || "@*#?!".contains(*c) => Some((c.to_string(), 1)),
+        Some(c) if is_name_char(*c) => {
+            let len = chars.iter().take_while(|c| is_name_char(**c)).count();
+            Some((chars[..len].iter().collect(), len))
+        }
+        _ => None,
+    }
+}
+
+// Adds one character of an expanded word, and to the glob pattern escaped if it was quoted
+fn push_expanded(out: &mut String, pattern: &mut String, c: char, literal: bool) {
+    out.push(c);
+    if literal && "*?[]\\".contains(c) {
+        pattern.push('\\');
+    }
+    pattern.push(c);
+}
+
+// Removes quotes, substitutes `~` and `$VAR` in a word and expands unquoted globs
+// An unquoted word that expands to nothing disappears, `$@` turns into one word per argument
+pub fn expand_word(word: &str, shell: &Shell) -> Result<Vec<String>, String> {
+    if word == "$@" || word == "\"$@\"" {
+        return Ok(shell.positional.clone());
+    }
+
+    let chars = word.chars().collect::<Vec<_>>();
+    let mut out = String::new();
+    // The same as `out` but with quoted glob characters escaped
+    let mut pattern = String::new();
+    let mut quoted = false;
+    let mut in_double = false;
+    let mut i = 0;
+
+    if chars.first() == Some(&'~') && (chars.len() == 1 || chars[1] == '/') {
+        let home = home_dir().unwrap_or_default();
+        for c in home.to_string_lossy().chars() {
+            push_expanded(&mut out, &mut pattern, c, true);
+        }
+        i = 1;
+    }
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\'' if !in_double => {
+                quoted = true;
+                let len = chars[i + 1..]
+                    .iter()
+                    .position(|c| *c == '\'')
+                    .unwrap_or(chars.len() - i - 1);
+                for c in &chars[i + 1..i + 1 + len] {
+                    push_expanded(&mut out, &mut pattern, *c, true);
+                }
+                i += len + 2;
+                continue;
+            }
+            '"' => {
+                quoted = true;
+                in_double = !in_double;
+            }
+            '\\' if i + 1 < chars.len() => {
+                let next = chars[i + 1];
+                if in_double && !"$`\"\\".contains(next) {
+                    push_expanded(&mut out, &mut pattern, c, true);
+                }
+                push_expanded(&mut out, &mut pattern, next, true);
+                i += 2;
+                continue;
+            }
+            '$' => {
+                if let Some((name, len)) = read_var_name(&chars[i + 1..]) {
+                    let value = match shell.var(&name) {
+                        Some(value) => value,
+                        None if shell.options.is_set("nounset") => {
+                            return Err(format!("{}: unbound variable", name))
+                        }
+                        None => String::new(),
+                    };
+                    for c in value.chars() {
+                        push_expanded(&mut out, &mut pattern, c, in_double);
+                    }
+                    i += len + 1;
+                    continue;
+                }
+                push_expanded(&mut out, &mut pattern, c, in_double);
+            }
+            _ => push_expanded(&mut out, &mut pattern, c, in_double),
+        }
+        i += 1;
+    }
+
+    if out.is_empty() && !quoted {
+        return Ok(Vec::new());
+    }
+    if !shell.options.is_set("noglob") && has_glob(&pattern) {
+        let matches = glob(&pattern, shell.options.is_set("dotglob"));
+        if !matches.is_empty() {
+            return Ok(matches);
+        }
+        if shell.options.is_set("nullglob") {
+            return Ok(Vec::new());
+        }
+    }
+    Ok(vec![out])
+}
+
+// Quotes a word so it reads back the same, for printing commands
+pub fn quote_word(word: &str) -> String {
+    if !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c))
+    {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', "'\\''"))
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum RedirectKind {
+    Read,
+    Write,
+    Append,
+    // `>|` writes even when noclobber is set
+    Clobber,
+    // `2>&1`, the target is a file descriptor
+    Duplicate,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Redirect {
+    pub fd: i32,
+    pub kind: RedirectKind,
+    pub target: String,
+}
+
+// Reads `<`, `>`, `>>`, `>|`, `>&` and `<&` with an optional fd in front, or `&>`,
+// at the start of a word; the target is empty when it is the next word
+fn parse_redirect(word: &str) -> Option<(Vec<(i32, RedirectKind)>, &str)> {
+    if let Some(target) = word.strip_prefix("&>") {
+        let both = vec![(1, RedirectKind::Write), (2, RedirectKind::Duplicate)];
+        return Some((both, target));
+    }
+
+    let digits = word.chars().take_while(|c| c.is_ascii_digit()).count();
+    let (fd, rest) = word.split_at(digits);
+    let (default_fd, kind, target) = [
+        (">>", 1, RedirectKind::Append),
+        (">|", 1, RedirectKind::Clobber),
+        (">&", 1, RedirectKind::Duplicate),
+        ("<&", 0, RedirectKind::Duplicate),
+        (">", 1, RedirectKind::Write),
+        ("<", 0, RedirectKind::Read),
+    ]
+    .iter()
+    .find_map(|(op, fd, kind)| Some((*fd, *kind, rest.strip_prefix(op)?)))?;
+
+    let fd = if digits == 0 {
+        default_fd
+    } else {
+        fd.parse().ok()?
+    };
+    Some((vec![(fd, kind)], target))
+}
+
+// Takes the redirections out of a command's words, in the order they have to be applied
+pub fn split_redirects(words: Vec<String>) -> Result<(Vec<String>, Vec<Redirect>), String> {
+    let mut rest = Vec::new();
+    let mut redirects = Vec::new();
+    let mut words = words.into_iter();
+
+    while let Some(word) = words.next() {
+        let (fds, target) = match parse_redirect(&word) {
+            Some(found) => found,
+            None => {
+                rest.push(word);
+                continue;
+            }
+        };
+        let target = match target {
+            "" => words
+                .next()
+                .ok_or_else(|| "syntax error near unexpected token `newline'".to_string())?,
+            target => target.to_string(),
+        };
+        for (fd, kind) in fds {
+            let target = match kind {
+                // `&>file` sends stderr wherever stdout goes
+                RedirectKind::Duplicate if fd == 2 && word.starts_with('&') => "1".to_string(),
+                _ => target.clone(),
+            };
+            redirects.push(Redirect { fd, kind, target });
+        }
+    }
+    Ok((rest, redirects))
+}
+
+// `NAME=value` words at the start of a command assign variables
+pub fn split_assignment(word: &str) -> Option<(&str, &str)> {
+    let (name, value) = word.split_once('=')?;
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => (),
+        _ => return None,
+    }
+    if chars.all(is_name_char) {
+        Some((name, value))
+    } else {
+        None
+    }
+}
+
+// A command with its redirections taken out, as shown by --debug-ast
+#[derive(Debug, PartialEq)]
+pub struct SimpleCommand {
+    pub words: Vec<String>,
+    pub redirects: Vec<Redirect>,
+}
+
+// One entry of a command list with every `&&` chained pipeline broken down into its commands
+#[derive(Debug, PartialEq)]
+pub struct AstItem {
+    pub background: bool,
+    pub chain: Vec<Vec<SimpleCommand>>,
+}
+
+// How a command line is split into lists, `&&` chains, pipelines and simple commands
+pub fn parse_ast(input: &str) -> Result<Vec<AstItem>, String> {
+    let parse_pipeline = |command: &String| {
+        split_unquoted(command, &["|"])
+            .into_iter()
+            .map(|(stage, _)| {
+                let (words, redirects) = split_redirects(split_words(&stage))?;
+                Ok(SimpleCommand { words, redirects })
+            })
+            .collect::<Result<Vec<_>, String>>()
+    };
+
+    split_list(input)
+        .into_iter()
+        .map(|item| {
+            Ok(AstItem {
+                background: item.background,
+                chain: item
+                    .commands
+                    .iter()
+                    .map(parse_pipeline)
+                    .collect::<Result<_, _>>()?,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,267 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::lexer::*;
+    use crate::shell::Shell;
+
+    #[test]
+    fn split_unquoted_test() {
+        assert_eq!(
+            split_unquoted("echo 'a;b'; ls \"c;d\" e\\;f", &[";"]),
+            vec![
+                ("echo 'a;b'".to_string(), Some(";")),
+                (" ls \"c;d\" e\\;f".to_string(), None)
+            ]
+        );
+        assert_eq!(
+            split_unquoted("f() { a; b }; c", &[";"]),
+            vec![
+                ("f() { a; b }".to_string(), Some(";")),
+                (" c".to_string(), None)
+            ]
+        );
+    }
+
+    #[test]
+    fn split_list_test() {
+        let list = split_list("a && b & c; ;d &");
+        assert_eq!(
+            list,
+            vec![
+                ListItem {
+                    commands: vec!["a ".to_string(), " b ".to_string()],
+                    background: true
+                },
+                ListItem {
+                    commands: vec![" c".to_string()],
+                    background: false
+                },
+                ListItem {
+                    commands: vec!["d ".to_string()],
+                    background: true
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn split_redirection_operators_test() {
+        let list = split_list("a 2>&1 && b &>log & c >| out");
+        assert_eq!(list[0].commands, vec!["a 2>&1 ", " b &>log "]);
+        assert!(list[0].background);
+        assert_eq!(split_unquoted(" c >| out", &["|"]).len(), 1);
+        assert_eq!(split_unquoted("a | b", &["|"]).len(), 2);
+    }
+
+    #[test]
+    fn strip_comments_test() {
+        assert_eq!(strip_comments("ls # list"), "ls ");
+        assert_eq!(strip_comments("echo a#b '# no'"), "echo a#b '# no'");
+        assert_eq!(strip_comments("# a\nls"), "\nls");
+    }
+
+    #[test]
+    fn split_words_test() {
+        assert_eq!(
+            split_words("  echo 'hello world'  \"a b\"c d\\ e "),
+            vec!["echo", "'hello world'", "\"a b\"c", "d\\ e"]
+        );
+    }
+
+    #[test]
+    fn complete_commands_test() {
+        let script = "a\nf() {\n  b\n}\necho 'x\ny'\nc \\\n  d\n";
+        assert_eq!(
+            complete_commands(script),
+            (
+                vec![
+                    (1, "a".to_string()),
+                    (2, "f() {\n  b\n}".to_string()),
+                    (5, "echo 'x\ny'".to_string()),
+                    (7, "c   d".to_string()),
+                ],
+                None
+            )
+        );
+        // An unclosed quote or brace at the end is not run but pointed at
+        assert_eq!(
+            complete_commands("a\necho 'x\ny\n"),
+            (
+                vec![(1, "a".to_string())],
+                Some((
+                    2,
+                    "unexpected end of file looking for the closing `'`".to_string()
+                ))
+            )
+        );
+        assert_eq!(
+            complete_commands("f() {\n  b\n").1,
+            Some((
+                1,
+                "unexpected end of file looking for the closing `}`".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn expand_word_test() {
+        let mut shell = Shell::new(HashMap::new());
+        shell.positional = vec!["one".to_string(), "two".to_string()];
+        shell.last_status = 3;
+
+        assert_eq!(expand_word("'$1'", &shell).unwrap(), vec!["$1"]);
+        assert_eq!(expand_word("\"$1 $2\"", &shell).unwrap(), vec!["one two"]);
+        assert_eq!(expand_word("${1}x", &shell).unwrap(), vec!["onex"]);
+        assert_eq!(expand_word("$#", &shell).unwrap(), vec!["2"]);
+        assert_eq!(expand_word("$?", &shell).unwrap(), vec!["3"]);
+        assert_eq!(expand_word("\"$@\"", &shell).unwrap(), vec!["one", "two"]);
+        assert_eq!(expand_word("a\\ b", &shell).unwrap(), vec!["a b"]);
+        assert_eq!(expand_word("$3", &shell).unwrap(), Vec::<String>::new());
+        assert_eq!(expand_word("${00}", &shell).unwrap(), Vec::<String>::new());
+        assert_eq!(expand_word("\"\"", &shell).unwrap(), vec![""]);
+    }
+
+    #[test]
+    fn expand_options_test() {
+        let mut shell = Shell::new(HashMap::new());
+        shell.options.set("nounset", true).unwrap();
+        assert!(expand_word("$VSH_SURELY_UNSET", &shell).is_err());
+        assert_eq!(expand_word("$#", &shell).unwrap(), vec!["0"]);
+
+        let pattern = format!("{}/Cargo.tom?", env!("CARGO_MANIFEST_DIR"));
+        let path = format!("{}/Cargo.toml", env!("CARGO_MANIFEST_DIR"));
+        assert_eq!(expand_word(&pattern, &shell).unwrap(), vec![path]);
+        assert_eq!(
+            expand_word(&format!("'{}'", pattern), &shell).unwrap(),
+            vec![pattern.clone()]
+        );
+        assert_eq!(
+            expand_word("/surely/not/*", &shell).unwrap(),
+            vec!["/surely/not/*"]
+        );
+
+        shell.options.set("noglob", true).unwrap();
+        assert_eq!(
+            expand_word(&pattern, &shell).unwrap(),
+            vec![pattern.clone()]
+        );
+        shell.options.set("noglob", false).unwrap();
+        shell.options.set("nullglob", true).unwrap();
+        assert!(expand_word("/surely/not/*", &shell).unwrap().is_empty());
+    }
+
+    #[test]
+    fn quote_word_test() {
+        assert_eq!(quote_word("plain-word"), "plain-word");
+        assert_eq!(quote_word("two words"), "'two words'");
+        assert_eq!(quote_word("it's"), "'it'\\''s'");
+        assert_eq!(quote_word(""), "''");
+    }
+
+    #[test]
+    fn split_redirects_test() {
+        let words = ["cmd", "a", ">out", "2>>", "log", "<in", "2>&1", "&>all"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let (rest, redirects) = split_redirects(words).unwrap();
+        assert_eq!(rest, vec!["cmd", "a"]);
+
+        let found = redirects
+            .iter()
+            .map(|r| (r.fd, r.kind, r.target.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            vec![
+                (1, RedirectKind::Write, "out"),
+                (2, RedirectKind::Append, "log"),
+                (0, RedirectKind::Read, "in"),
+                (2, RedirectKind::Duplicate, "1"),
+                (1, RedirectKind::Write, "all"),
+                (2, RedirectKind::Duplicate, "1"),
+            ]
+        );
+
+        assert!(split_redirects(vec!["echo".to_string(), ">".to_string()]).is_err());
+        let (rest, redirects) = split_redirects(vec!["'>'".to_string(), "2".to_string()]).unwrap();
+        assert_eq!(rest.len(), 2);
+        assert!(redirects.is_empty());
+    }
+
+    #[test]
+    fn split_assignment_test() {
+        assert_eq!(split_assignment("A=1"), Some(("A", "1")));
+        assert_eq!(split_assignment("_a=b=c"), Some(("_a", "b=c")));
+        assert_eq!(split_assignment("1A=1"), None);
+        assert_eq!(split_assignment("--flag=1"), None);
+    }
+
+    #[test]
+    fn expand_aliases_test() {
+        let aliases = [
+            ("ll", "ls -l"),
+            ("ls", "ls -F"),
+            ("sudo", "sudo "),
+            ("g", "grep $1 -r $2"),
+            ("each", "for_each $@ done"),
+            ("awk1", "awk '{print $1}'"),
+            ("both", "cd /; ll"),
+        ]
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect::<HashMap<_, _>>();
+
+        assert_eq!(expand_aliases("ll -a", &aliases), "ls -F -l -a");
+        assert_eq!(
+            expand_aliases("echo ll | ll", &aliases),
+            "echo ll | ls -F -l"
+        );
+        assert_eq!(expand_aliases("sudo ll /", &aliases), "sudo ls -F -l /");
+        assert_eq!(
+            expand_aliases("g foo src > out", &aliases),
+            "grep foo -r src > out"
+        );
+        assert_eq!(expand_aliases("g foo", &aliases), "grep foo -r ");
+        assert_eq!(
+            expand_aliases("each a 'b c'", &aliases),
+            "for_each a 'b c' done"
+        );
+        assert_eq!(
+            expand_aliases("awk1 file", &aliases),
+            "awk '{print $1}' file"
+        );
+        assert_eq!(
+            expand_aliases("both && ls", &aliases),
+            "cd /; ls -F -l && ls -F"
+        );
+        assert_eq!(expand_aliases("time ll", &aliases), "time ls -F -l");
+        assert_eq!(expand_aliases("\\ls; 'll'", &aliases), "\\ls; 'll'");
+    }
+
+    #[test]
+    fn parse_ast_test() {
+        let ast = parse_ast("a 2>/dev/null | b && c; d &").unwrap();
+        assert_eq!(ast.len(), 2);
+        assert!(!ast[0].background);
+        assert_eq!(ast[0].chain.len(), 2);
+        assert_eq!(ast[0].chain[0][0].words, vec!["a".to_string()]);
+        assert_eq!(
+            ast[0].chain[0][0].redirects,
+            vec![Redirect {
+                fd: 2,
+                kind: RedirectKind::Write,
+                target: "/dev/null".to_string()
+            }]
+        );
+        assert_eq!(ast[0].chain[0][1].words, vec!["b".to_string()]);
+        assert!(ast[1].background);
+        assert!(parse_ast("echo >").is_err());
+    }
+}
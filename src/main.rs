@@ -5,15 +5,57 @@
  */
 
 mod builtins;
+mod cli;
+#[cfg(test)]
+mod cli_test;
 mod command;
 mod eval;
+#[cfg(test)]
+mod eval_test;
+mod jobs;
+#[cfg(test)]
+mod jobs_test;
+mod lexer;
+#[cfg(test)]
+mod lexer_test;
+mod options;
+#[cfg(test)]
+mod options_test;
 mod prompt;
 mod repl;
+mod shell;
 mod theme;
 mod utils;
 
+use std::env;
+use std::process;
+
+use cli::{parse_args, HELP, USAGE};
 use repl::Repl;
 
 fn main() {
-    Repl::new().start_shell().unwrap();
+    let mut argv = env::args();
+    let argv0 = argv.next().unwrap_or_default();
+    let args = match parse_args(&argv0, argv.collect()) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("vsh: {}\n{}", e, USAGE);
+            process::exit(2);
+        }
+    };
+    if args.help {
+        println!("{}\n\n{}", USAGE, HELP);
+        return;
+    }
+    if args.version {
+        println!("vsh {}", env!("CARGO_PKG_VERSION"));
+        return;
+    }
+    if args.print_config {
+        process::exit(Repl::new(args).print_config());
+    }
+    if args.check_config {
+        process::exit(Repl::new(args).check_config());
+    }
+    Repl::new(args).start_shell().unwrap();
 }
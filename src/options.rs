@@ -0,0 +1,93 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::HashSet;
+
+// Options of `set -o`, with the single letter `set` also takes for them
+pub const SET_OPTIONS: &[(&str, Option<char>)] = &[
+    ("errexit", Some('e')),
+    ("ignoreeof", None),
+    ("noclobber", Some('C')),
+    ("noglob", Some('f')),
+    ("nounset", Some('u')),
+    ("pipefail", None),
+    ("xtrace", Some('x')),
+];
+
+// Options of `shopt`
+pub const SHOPT_OPTIONS: &[&str] = &["autocd", "dotglob", "nullglob"];
+
+// Every option that is turned on, `set` and `shopt` options share one namespace
+#[derive(Clone, Default)]
+pub struct Options {
+    enabled: HashSet<&'static str>,
+}
+
+impl Options {
+    pub fn is_set(&self, name: &str) -> bool {
+        self.enabled.contains(name)
+    }
+
+    // Turns an option of either `set -o` or `shopt` on or off
+    pub fn set(&mut self, name: &str, on: bool) -> Result<(), String> {
+        let name = SET_OPTIONS
+            .iter()
+            .map(|(name, _)| *name)
+            .chain(SHOPT_OPTIONS.iter().copied())
+            .find(|known| *known == name)
+            .ok_or_else(|| format!("{}: invalid option name", name))?;
+        if on {
+            self.enabled.insert(name);
+        } else {
+            self.enabled.remove(name);
+        }
+        Ok(())
+    }
+}
+
+// Reads `-eu`, `+x`, `-o pipefail` and `+o noglob` the way both `set` and the command line take them
+// Returns the toggles in order and, if `--` or a non option argument was found,
+// what comes after as new positional parameters
+#[allow(clippy::type_complexity)]
+pub fn parse_set_args(
+    args: Vec<String>,
+) -> Result<(Vec<(&'static str, bool)>, Option<Vec<String>>), String> {
+    let mut toggles = Vec::new();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        let on = match arg.chars().next() {
+            _ if arg == "--" || arg == "-" => return Ok((toggles, Some(args.collect()))),
+            Some('-') => true,
+            Some('+') => false,
+            _ => {
+                let positional = std::iter::once(arg).chain(args).collect();
+                return Ok((toggles, Some(positional)));
+            }
+        };
+
+        for flag in arg.chars().skip(1) {
+            let name = if flag == 'o' {
+                let name = args
+                    .next()
+                    .ok_or_else(|| "-o: option requires an argument".to_string())?;
+                SET_OPTIONS
+                    .iter()
+                    .find(|(known, _)| *known == name)
+                    .map(|(known, _)| *known)
+                    .ok_or_else(|| format!("{}: invalid option name", name))?
+            } else {
+                SET_OPTIONS
+                    .iter()
+                    .find(|(_, letter)| *letter == Some(flag))
+                    .map(|(known, _)| *known)
+                    .ok_or_else(|| format!("{}{}: invalid option", &arg[..1], flag))?
+            };
+            toggles.push((name, on));
+        }
+    }
+    Ok((toggles, None))
+}
@@ -0,0 +1,73 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::options::*;
+    use crate::shell::Shell;
+    use crate::utils::Config;
+
+    fn strings(v: &[&str]) -> Vec<String> {
+        v.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_set_args_test() {
+        let (toggles, positional) =
+            parse_set_args(strings(&["-eu", "+x", "-o", "pipefail"])).unwrap();
+        assert_eq!(
+            toggles,
+            vec![
+                ("errexit", true),
+                ("nounset", true),
+                ("xtrace", false),
+                ("pipefail", true)
+            ]
+        );
+        assert_eq!(positional, None);
+
+        let (toggles, positional) = parse_set_args(strings(&["-eo", "noglob", "a", "-b"])).unwrap();
+        assert_eq!(toggles, vec![("errexit", true), ("noglob", true)]);
+        assert_eq!(positional, Some(strings(&["a", "-b"])));
+
+        let (_, positional) = parse_set_args(strings(&["--"])).unwrap();
+        assert_eq!(positional, Some(Vec::new()));
+
+        assert!(parse_set_args(strings(&["-q"])).is_err());
+        assert!(parse_set_args(strings(&["-o", "dotglob"])).is_err());
+        assert!(parse_set_args(strings(&["-o"])).is_err());
+    }
+
+    #[test]
+    fn options_test() {
+        let mut options = Options::default();
+        options.set("pipefail", true).unwrap();
+        options.set("dotglob", true).unwrap();
+        assert!(options.is_set("pipefail"));
+        assert!(options.is_set("dotglob"));
+
+        options.set("pipefail", false).unwrap();
+        assert!(!options.is_set("pipefail"));
+        assert!(options.set("nonsense", true).is_err());
+    }
+
+    #[test]
+    fn config_options_test() {
+        let config = |text: &str| toml::from_str::<Config>(text).unwrap();
+        let mut shell = Shell::new(HashMap::new());
+        shell.apply_config(config("[options]\npipefail = true\nnullglob = true"));
+        assert!(shell.options.is_set("pipefail"));
+        assert!(shell.options.is_set("nullglob"));
+
+        // Leaving an option out of the config turns it back off
+        shell.apply_config(config("[options]\nnullglob = true"));
+        assert!(!shell.options.is_set("pipefail"));
+        assert!(shell.options.is_set("nullglob"));
+        shell.apply_config(config(""));
+        assert!(!shell.options.is_set("nullglob"));
+    }
+}
@@ -6,10 +6,11 @@ use std::collections::HashMap;
  * file, You can obtain one at http://mozilla.org/MPL/2.0/.
  */
 use crate::theme::{
-    context::{Context, DirectoryContext, SubContext, ThemeContext},
+    context::{Context, DirectoryContext, EnvironmentContext, SubContext, ThemeContext},
     format::construct_colored,
     parser::Theme,
 };
+use crate::utils::home_dir;
 
 // This struct is to know what prompt appearance was at STARTUP
 pub struct Prompt<'a> {
@@ -21,6 +22,9 @@ pub struct Prompt<'a> {
 pub struct PromptInfo {
     pub terminated: bool,
     pub exit_code: Option<i32>,
+    // Name of the signal that killed the previous program, e.g. SIGSEGV
+    pub signal: Option<String>,
+    pub core_dumped: bool,
 }
 
 impl SubContext for PromptInfo {
@@ -31,13 +35,16 @@ impl SubContext for PromptInfo {
             Some(x) => vars.insert("exit_code".to_string(), x.to_string()),
             None => vars.insert("exit_code".to_string(), "".to_string()),
         };
+        vars.insert(
+            "signal".to_string(),
+            self.signal.clone().unwrap_or_default(),
+        );
+        vars.insert("core_dumped".to_string(), self.core_dumped.to_string());
 
         vars
     }
 }
 
-// This Struct Is to get the info from `.vshrc.toml`
-
 impl<'a> Prompt<'a> {
     pub fn new(theme: Theme<'a>) -> Self {
         Self { theme }
@@ -47,18 +54,24 @@ impl<'a> Prompt<'a> {
         // The following lines could not be created into a function due to compiler optimization
         // issue, atleast thats what I think
         let current_dir = {
+            // The directory may be gone, or not be UTF-8
             let current_path = std::env::current_dir()
-                .unwrap()
-                .into_os_string()
-                .into_string()
-                .unwrap();
-            let home = std::env::var("HOME").unwrap();
-            current_path.replace(&home, "~")
+                .map(|path| path.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let home = home_dir().unwrap_or_default();
+            let home = home.to_string_lossy();
+            match current_path.strip_prefix(home.as_ref()) {
+                Some(rest) if home != "/" && (rest.is_empty() || rest.starts_with('/')) => {
+                    format!("~{}", rest)
+                }
+                _ => current_path,
+            }
         };
         let dir_ctx = DirectoryContext::new(current_dir);
 
         let mut ctx = Context::new();
         ctx.from_sub_context(&dir_ctx);
+        ctx.from_sub_context(&EnvironmentContext);
         ctx.from_sub_context(&chrono::offset::Utc::now());
         ctx.from_sub_context(&chrono::offset::Local::now());
         ctx.extend(general_ctx);
@@ -78,11 +91,15 @@ impl PromptInfo {
         Self {
             terminated,
             exit_code,
+            signal: None,
+            core_dumped: false,
         }
     }
 
     pub fn default(&mut self) {
         self.terminated = false;
         self.exit_code = None;
+        self.signal = None;
+        self.core_dumped = false;
     }
 }
@@ -6,24 +6,36 @@
 
 #![warn(unreachable_code)]
 
+use std::collections::HashMap;
 use std::env;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
 use std::process;
 use std::thread;
 
-use crate::eval::{CommandError, Vshcommand};
+use crate::builtins;
+use crate::builtins::source::Source;
+use crate::cli::Args;
+use crate::command::Builtin;
+use crate::eval::{status_of, CommandError, Vshcommand};
 use crate::prompt::{Prompt, PromptInfo};
+use crate::shell::Shell;
 use crate::theme::context::Context;
 use crate::theme::context::SessionContext;
 use crate::theme::context::ThemeContext;
 use crate::theme::parser::parse_theme;
 use crate::theme::parser::Span;
-use crate::utils::{fetch_data, get_alias, get_toml, get_theme, PromptEffects};
+use crate::utils::{
+    check_permissions, check_theme, config_dir, config_path, describe_config, get_theme,
+    history_path, legacy_config_path, load_config, migrate_config, os_error_text, signal_name,
+    validate_theme, MergedConfig, PromptEffects, TRAPPABLE,
+};
 
 use colored::Colorize;
-use libc::c_int;
 use signal_hook::consts::signal::*;
+use signal_hook::iterator::Signals;
 use signal_hook::low_level;
 
 use rustyline::completion::FilenameCompleter;
@@ -32,16 +44,25 @@ use rustyline::error::ReadlineError;
 use rustyline::highlight::MatchingBracketHighlighter;
 use rustyline::hint::HistoryHinter;
 use rustyline::validate::MatchingBracketValidator;
-use rustyline::{CompletionType, Config, EditMode, Editor};
-
-#[cfg(not(feature = "extended-siginfo"))]
-use signal_hook::iterator::Signals;
+use rustyline::{CompletionType, Config, EditMode, Editor, EventHandler, Helper, KeyEvent};
 
-pub struct Repl;
+pub struct Repl {
+    args: Args,
+    // Whether -i was given, or stdin is a terminal and there is no -c, only then init.vsh is run
+    interactive: bool,
+    // None with --no-history and -c
+    history_file: Option<PathBuf>,
+}
 
 impl Repl {
-    pub fn new() -> Self {
-        Self {}
+    pub fn new(args: Args) -> Self {
+        let interactive =
+            args.interactive || (args.command.is_none() && unsafe { libc::isatty(0) } == 1);
+        Self {
+            args,
+            interactive,
+            history_file: None,
+        }
     }
 
     pub fn start_shell(&mut self) -> io::Result<()> {
@@ -54,70 +75,134 @@ impl Repl {
 
         let mut rl = Editor::with_config(prconf);
 
-        let home_dir = env::var("HOME").unwrap();
+        if !self.args.no_history && self.args.command.is_none() {
+            let history_file = history_path();
+            if let Some(dir) = history_file.parent() {
+                fs::create_dir_all(dir).ok();
+            }
+            self.history_file = Some(history_file);
+        }
 
-        const SIGNALS: &[c_int] = &[SIGTSTP, SIGINT];
-        let mut signals = Signals::new(SIGNALS).expect("Failed to create signals struct");
+        let mut signals = Signals::new(TRAPPABLE).expect("Failed to create signals struct");
 
         let _handler = signals.handle();
         let mut promptinfo = PromptInfo::new(false, None);
 
-        if rl
-            .load_history(&format!("{}/.vsh_history", home_dir))
-            .is_err()
-        {
-            eprintln!("vsh: No previous history.");
-            if File::create(format!("{}/.vsh_history", home_dir)).is_err() {
-                eprintln!("vsh: Could not create history file!");
+        if let Some(history_file) = &self.history_file {
+            if rl.load_history(history_file).is_err() {
+                eprintln!("vsh: No previous history.");
+                if File::create(history_file).is_err() {
+                    eprintln!("vsh: Could not create history file!");
+                }
             }
         }
 
+        let mut shell = Shell::new(HashMap::new());
+        shell.config_path = self.choose_config();
+        shell.profile = self.profile();
+        shell.allow_insecure_config = self.allow_insecure();
+        shell.debug_ast = self.args.debug_ast;
+        shell.interactive = self.interactive;
+        // A broken config is reported and left out, vsh still starts with the defaults
+        shell.reload_config();
+        // Options from the command line win over the ones from the config file
+        for (name, on) in &self.args.options {
+            shell.options.set(name, *on).unwrap();
+        }
+        rl.bind_sequence(
+            KeyEvent::from(' '),
+            EventHandler::Conditional(Box::new(shell.abbreviations.clone())),
+        );
+
+        // Trapped signals are only queued here, the main loop runs their handlers
+        let queue = shell.signals.clone();
         thread::spawn(move || {
             for signal in signals.forever() {
                 match signal {
+                    _ if queue.is_trapped(signal) => queue.push(signal),
+                    // SIGHUP ends the shell from the main loop, which hangs up the jobs and
+                    // saves the history on the way out; the line editor does not stop reading
+                    // for a signal, so stdin is pointed at /dev/null to end the line
+                    SIGHUP => {
+                        queue.push(signal);
+                        if let Ok(null) = File::open("/dev/null") {
+                            unsafe { libc::dup2(null.as_raw_fd(), 0) };
+                        }
+                    }
                     SIGTSTP => (), // ctrlz
                     SIGINT => (),  // ctrlc
                     _ => low_level::emulate_default_handler(signal).unwrap(),
                 }
             }
         });
-
-        let config_data = match get_toml(fetch_data()) {
-            Ok(x) => x,
-            Err(err) => {
-                println!("{:?}", err);
-                get_toml(String::from("")).unwrap() // Unwrap free
+        // profile.vsh for login shells, then init.vsh for interactive ones
+        let mut scripts = Vec::new();
+        if self.args.login {
+            scripts.push((config_dir().join("profile.vsh"), false));
+        }
+        if self.interactive && !self.args.norc {
+            match &self.args.rcfile {
+                Some(rcfile) => scripts.push((rcfile.clone(), true)),
+                None => scripts.push((config_dir().join("init.vsh"), false)),
             }
-        };
+        }
+        for (script, required) in scripts {
+            if let Err(CommandError::Exit(code)) = Self::run_file(&mut shell, &script, required) {
+                self.exit(&mut rl, &mut shell, code);
+            }
+        }
 
-        let aliases = get_alias(&config_data);
-        let theme = get_theme(&config_data);
-
-        let theme = match parse_theme(Span::new(&theme)) {
-            Ok(t) => t.1,
-            Err(error) => {
-                if let nom::Err::Error(x) = error {
-                    eprintln!("{}{}", " ".repeat(x.input.get_column()), "↑".red());
-                    eprintln!(
-                        "{}{}{}",
-                        " ".repeat(x.input.get_column()),
-                        "Parse error near: ".red(),
-                        x.input.fragment().bright_red().bold()
-                    );
+        if let Some(command) = self.args.command.clone() {
+            shell.positional = self.args.positional.clone();
+            let code = match Vshcommand::run(command, &mut shell) {
+                Err(CommandError::Error(e)) => {
+                    eprintln!("vsh: {}", e);
+                    1
                 }
-                parse_theme(Span::new("&[#7393B3]`[`{{current_dir}}`] `"))
-                    .unwrap()
-                    .1
-            }
-        };
+                result => status_of(&result),
+            };
+            self.exit(&mut rl, &mut shell, code);
+        }
+
+        // The theme text in use, a theme that fails to parse keeps the previous one, or the
+        // default one at startup; `tried` is the last text parsed, so errors show up once
+        let mut theme = String::from("&[#7393B3]`[`{{current_dir}}`] `");
+        let mut tried = None;
 
         let mut general_ctx = Context::new();
         general_ctx.from_sub_context(&SessionContext::new());
 
+        // Ctrl+D presses in a row that ignoreeof swallowed
+        let mut ignored_eofs = 0;
+
         loop {
+            if let Err(CommandError::Exit(code)) = Vshcommand::run_pending_traps(&mut shell) {
+                self.exit(&mut rl, &mut shell, code);
+            }
+            // Edits to the config file apply at the next prompt
+            if shell.config_changed() && !shell.reload_config() {
+                eprintln!("vsh: config: keeping the previous config");
+            }
+            let source = match &self.args.theme {
+                Some(source) => source.clone(),
+                None => get_theme(&shell.config),
+            };
+            if tried.as_ref() != Some(&source) {
+                match parse_theme(Span::new(&source)) {
+                    Ok(_) => theme = source.clone(),
+                    Err(error) => Self::theme_error(error),
+                }
+                tried = Some(source);
+            }
+
+            for job in shell.jobs.reap() {
+                eprintln!("[{}]  {:<10} {}", job.id, job.state(), job.command);
+            }
             general_ctx.from_sub_context(&promptinfo);
 
-            let prompt = Prompt::new(theme.clone()).generate_prompt(&general_ctx);
+            // A parsed theme borrows its text, so it is parsed again for every prompt
+            let (_, parsed) = parse_theme(Span::new(&theme)).unwrap();
+            let prompt = Prompt::new(parsed).generate_prompt(&general_ctx);
 
             let helper = PromptEffects {
                 completer: FilenameCompleter::new(),
@@ -125,39 +210,50 @@ impl Repl {
                 hinter: HistoryHinter {},
                 colored_prompt: prompt.clone(),
                 validator: MatchingBracketValidator::new(),
-                ctx: config_data.clone(),
+                ctx: shell.config.clone(),
+                commands: builtins::names()
+                    .into_iter()
+                    .map(str::to_string)
+                    .chain(shell.aliases.keys().cloned())
+                    .chain(shell.functions.keys().cloned())
+                    .collect(),
+                autocd: shell.options.is_set("autocd"),
+                abbreviations: shell.abbreviations.clone(),
             };
 
             rl.set_helper(Some(helper));
 
             let readline = rl.readline(prompt.as_str());
+            // A hangup closes the terminal too, so reading fails as soon as it arrives
+            if let Err(CommandError::Exit(code)) = Vshcommand::run_pending_traps(&mut shell) {
+                self.exit(&mut rl, &mut shell, code);
+            }
 
             match readline {
                 Ok(x) => {
+                    ignored_eofs = 0;
+                    let x = shell.abbreviations.expand_line(x);
                     rl.add_history_entry(x.as_str());
 
-                    if let Err(e) = Vshcommand::run(x, &aliases) {
+                    if let Err(e) = Vshcommand::run(x, &mut shell) {
                         match e {
-                            CommandError::Exit => {
-                                if rl
-                                    .save_history(&format!("{}/.vsh_history", home_dir))
-                                    .is_err()
-                                {
-                                    eprintln!("vsh: Could not save command history");
-                                }
-                                process::exit(0);
-                            }
+                            CommandError::Exit(code) => self.exit(&mut rl, &mut shell, code),
                             CommandError::Error(x) => {
                                 eprintln!("vsh: {}", x);
                                 promptinfo.default();
                             }
-                            CommandError::Terminated(_) => {
-                                println!("\r");
+                            CommandError::Terminated(code) => {
+                                if code == 128 + libc::SIGINT {
+                                    println!("\r");
+                                }
                                 promptinfo.terminated = true;
-                                promptinfo.exit_code = None;
+                                promptinfo.exit_code = Some(code);
+                                promptinfo.signal =
+                                    signal_name(code - 128).map(|name| format!("SIG{}", name));
+                                promptinfo.core_dumped = shell.core_dumped;
                             }
-                            CommandError::Finished(code) => {
-                                promptinfo.terminated = false;
+                            CommandError::Finished(code) | CommandError::Return(code) => {
+                                promptinfo.default();
                                 promptinfo.exit_code = Some(code);
                             }
                         }
@@ -166,19 +262,240 @@ impl Repl {
                     }
                 }
                 Err(ReadlineError::Interrupted) => println!(),
-                Err(ReadlineError::Eof) => break,
+                // Like bash, ignoreeof gives in after ten Ctrl+D in a row
+                Err(ReadlineError::Eof)
+                    if shell.options.is_set("ignoreeof")
+                        && self.interactive
+                        && ignored_eofs < 10 =>
+                {
+                    ignored_eofs += 1;
+                    eprintln!("Use \"exit\" to leave the shell.");
+                }
+                // Ctrl+D, or the end of input when not running interactively
+                Err(ReadlineError::Eof) => {
+                    let code = shell.last_status;
+                    self.exit(&mut rl, &mut shell, code)
+                }
                 Err(err) => {
                     println!("vsh: Unexpected Error, please report the error on: https://github.com/xmantle/vsh/issues \n{:?}", err);
                     break;
                 }
             }
-            if rl
-                .save_history(&format!("{}/.vsh_history", home_dir))
-                .is_err()
-            {
+            self.save_history(&mut rl);
+        }
+        Ok(())
+    }
+
+    // Points at where a theme stopped parsing
+    fn theme_error(error: nom::Err<nom::error::Error<Span>>) {
+        if let nom::Err::Error(x) = error {
+            eprintln!("{}{}", " ".repeat(x.input.get_column()), "↑".red());
+            eprintln!(
+                "{}{}{}",
+                " ".repeat(x.input.get_column()),
+                "Parse error near: ".red(),
+                x.input.fragment().bright_red().bold()
+            );
+        }
+    }
+
+    // The config file in use: the one given with --config, otherwise config.toml, or the legacy
+    // ~/.vshrc.toml while config.toml does not exist
+    fn config_file(&self) -> PathBuf {
+        if let Some(path) = &self.args.config {
+            return path.clone();
+        }
+        let path = config_path();
+        match legacy_config_path() {
+            Some(legacy) if legacy.exists() && !path.exists() => legacy,
+            _ => path,
+        }
+    }
+
+    // The config file for the shell, saying which one is in effect when the legacy one is
+    // around and offering to move it into place at an interactive terminal
+    fn choose_config(&self) -> PathBuf {
+        let path = self.config_file();
+        let legacy = match legacy_config_path() {
+            Some(legacy) if self.args.config.is_none() && legacy.exists() => legacy,
+            _ => return path,
+        };
+        let new = config_path();
+        if path != legacy {
+            eprintln!(
+                "vsh: {} is in effect, the legacy {} is ignored",
+                new.display(),
+                legacy.display()
+            );
+            return path;
+        }
+
+        eprintln!(
+            "vsh: using the legacy config {}, vsh now reads {}",
+            legacy.display(),
+            new.display()
+        );
+        if self.args.command.is_some() || unsafe { libc::isatty(0) } != 1 {
+            return legacy;
+        }
+        eprint!("vsh: move it there now? [y/N] ");
+        let mut answer = String::new();
+        if io::stdin().read_line(&mut answer).is_err() || !answer.trim().eq_ignore_ascii_case("y") {
+            return legacy;
+        }
+        match migrate_config(&legacy, &new) {
+            Ok(()) => {
+                eprintln!("vsh: moved to {}", new.display());
+                new
+            }
+            Err(e) => {
+                eprintln!("vsh: {}: {}", new.display(), os_error_text(&e));
+                legacy
+            }
+        }
+    }
+
+    // The profile asked for with --profile or $VSH_PROFILE, profiles are matched otherwise
+    fn profile(&self) -> Option<String> {
+        self.args
+            .profile
+            .clone()
+            .or_else(|| env::var("VSH_PROFILE").ok().filter(|name| !name.is_empty()))
+    }
+
+    // Whether files other users can change are loaded, from --allow-insecure-config or
+    // VSH_ALLOW_INSECURE_CONFIG=1 for login shells that take no flags
+    fn allow_insecure(&self) -> bool {
+        self.args.allow_insecure_config
+            || env::var("VSH_ALLOW_INSECURE_CONFIG").is_ok_and(|value| value == "1")
+    }
+
+    // Reads and merges the config files without creating a default one, None when the file
+    // given with --config cannot be read
+    fn read_config(&self) -> Option<MergedConfig> {
+        let path = self.config_file();
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            // Without a config file the defaults are used, which are fine
+            Err(e) if e.kind() == io::ErrorKind::NotFound && self.args.config.is_none() => {
+                String::new()
+            }
+            Err(e) => {
+                eprintln!("vsh: {}: {}", path.display(), os_error_text(&e));
+                return None;
+            }
+        };
+        Some(load_config(
+            &path,
+            &text,
+            self.profile().as_deref(),
+            self.allow_insecure(),
+        ))
+    }
+
+    // For `vsh --check-config`, prints what is wrong with the config files and the theme in
+    // use and returns the exit status
+    pub fn check_config(&self) -> i32 {
+        let path = self.config_file();
+        let mut merged = match self.read_config() {
+            Some(merged) => merged,
+            None => return 1,
+        };
+
+        let mut failed = false;
+        match &self.args.theme {
+            Some(theme) => {
+                if let Err(e) = validate_theme(theme) {
+                    eprintln!("vsh: --theme: {}", e);
+                    failed = true;
+                }
+            }
+            None => {
+                let config = merged.config().unwrap_or_default();
+                // The problem points into the file the theme came from
+                let origin = merged.origins.get("prompt.theme").cloned();
+                let text = origin
+                    .as_ref()
+                    .and_then(|origin| fs::read_to_string(origin).ok())
+                    .unwrap_or_default();
+                if let Some(problem) = check_theme(&text, &get_theme(&config), origin.is_some()) {
+                    merged
+                        .problems
+                        .push((origin.unwrap_or_else(|| path.clone()), problem));
+                }
+            }
+        }
+
+        for (file, problem) in &merged.problems {
+            eprintln!("vsh: {}", problem.describe(file));
+        }
+        if merged.problems.is_empty() && !failed {
+            println!("{}: ok", path.display());
+            0
+        } else {
+            1
+        }
+    }
+
+    // For `vsh --print-config`, prints the merged config with the file of every value
+    pub fn print_config(&self) -> i32 {
+        let merged = match self.read_config() {
+            Some(merged) => merged,
+            None => return 1,
+        };
+        for (file, problem) in &merged.problems {
+            eprintln!("vsh: {}", problem.describe(file));
+        }
+        println!("{}", describe_config(&merged));
+        if merged.config().is_some() {
+            0
+        } else {
+            1
+        }
+    }
+
+    fn save_history<H: Helper>(&self, rl: &mut Editor<H>) {
+        if let Some(history_file) = &self.history_file {
+            if rl.save_history(history_file).is_err() {
                 eprintln!("vsh: Could not save command history");
             }
         }
-        Ok(())
+    }
+
+    // Runs a startup or logout script in the current shell, a missing one is only an error if `required`
+    fn run_file(shell: &mut Shell, path: &Path, required: bool) -> Result<(), CommandError> {
+        if !required && !path.exists() {
+            return Ok(());
+        }
+        if let Some(problem) = check_permissions(path, shell.allow_insecure_config) {
+            eprintln!("vsh: {}", problem.describe(path));
+            if !shell.allow_insecure_config {
+                return Ok(());
+            }
+        }
+        let result = Source::run(shell, vec![path.to_string_lossy().to_string()]);
+        if let Err(CommandError::Error(e)) = &result {
+            eprintln!("vsh: {}", e);
+        }
+        result
+    }
+
+    fn exit<H: Helper>(&self, rl: &mut Editor<H>, shell: &mut Shell, code: i32) -> ! {
+        // logout.vsh runs before the EXIT trap, an `exit` in either decides the final status
+        let code = if self.interactive || self.args.login {
+            match Self::run_file(shell, &config_dir().join("logout.vsh"), false) {
+                Err(CommandError::Exit(code)) => code,
+                _ => code,
+            }
+        } else {
+            code
+        };
+        let code = match Vshcommand::run_trap("EXIT", shell) {
+            Err(CommandError::Exit(code)) => code,
+            _ => code,
+        };
+        shell.jobs.hangup();
+        self.save_history(rl);
+        process::exit(code);
     }
 }
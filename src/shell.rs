@@ -0,0 +1,224 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use crate::builtins;
+use crate::jobs::Jobs;
+use crate::options::Options;
+use crate::utils::{
+    apply_env, fetch_data, find_in_path, get_alias, load_config, Abbreviations, CommandNotFound,
+    Config, SignalQueue,
+};
+
+// How deep functions and sourced files can nest, deeper calls fail instead of running out of stack
+pub const MAX_DEPTH: usize = 500;
+
+// Everything a command can change about the running shell lives here,
+// so builtins can inspect and modify it between commands
+pub struct Shell {
+    pub aliases: HashMap<String, String>,
+    pub functions: HashMap<String, String>,
+    // $1, $2, ... of the running function or sourced file
+    pub positional: Vec<String>,
+    // Status of the last command, i.e. `$?`
+    pub last_status: i32,
+    // How many functions and sourced files are running, `return` needs at least one
+    pub depth: usize,
+    // Commands set with `trap`, keyed by signal name without `SIG`, or EXIT and ERR
+    pub traps: HashMap<String, String>,
+    pub signals: SignalQueue,
+    // Set while a trap runs so it cannot trigger itself
+    pub in_trap: bool,
+    pub jobs: Jobs,
+    // PID of the last background job, i.e. `$!`
+    pub last_background: Option<u32>,
+    // User and system time of the foreground children reaped so far, `time` reports the difference
+    pub child_usage: (Duration, Duration),
+    // Options turned on with `set`, `shopt`, the config file or the command line
+    pub options: Options,
+    // Whether the last foreground program killed by a signal dumped core
+    pub core_dumped: bool,
+    // The [command_not_found] table of the config file
+    pub command_not_found: CommandNotFound,
+    // Shared with the line editor, which expands them as they are typed
+    pub abbreviations: Abbreviations,
+    // The config file in use, `alias --save` writes to it
+    pub config_path: PathBuf,
+    // The `[profile.<name>]` asked for, instead of the ones whose `match` rule fits
+    pub profile: Option<String>,
+    // Load config and startup files that other users can change, set with
+    // --allow-insecure-config
+    pub allow_insecure_config: bool,
+    // The config as last read, kept when an edit breaks it
+    pub config: Config,
+    // The config files read with their modification times, a change to any triggers a reload
+    pub config_files: Vec<(PathBuf, Option<SystemTime>)>,
+    // Print how each command line is parsed, set with --debug-ast
+    pub debug_ast: bool,
+    // Whether the shell reads commands from a terminal or got -i, an error like an unbound
+    // variable under `set -u` only ends a shell that is not
+    pub interactive: bool,
+    // `file:line` of the command running from a sourced file, messages start with it
+    pub location: Option<String>,
+}
+
+// What a command name turns into, in the order vsh tries them
+#[derive(Debug, PartialEq)]
+pub enum Resolution {
+    Alias(String),
+    Function(String),
+    Builtin,
+    File(PathBuf),
+}
+
+impl Shell {
+    pub fn new(aliases: HashMap<String, String>) -> Self {
+        Self {
+            aliases,
+            functions: HashMap::new(),
+            positional: Vec::new(),
+            last_status: 0,
+            depth: 0,
+            traps: HashMap::new(),
+            signals: SignalQueue::default(),
+            in_trap: false,
+            jobs: Jobs::default(),
+            last_background: None,
+            child_usage: (Duration::ZERO, Duration::ZERO),
+            options: Options::default(),
+            core_dumped: false,
+            command_not_found: CommandNotFound::default(),
+            abbreviations: Abbreviations::default(),
+            config_path: PathBuf::new(),
+            config: Config::default(),
+            profile: None,
+            allow_insecure_config: false,
+            config_files: Vec::new(),
+            debug_ast: false,
+            interactive: false,
+            location: None,
+        }
+    }
+
+    // Value of `$name`, positional parameters first and then the environment
+    pub fn var(&self, name: &str) -> Option<String> {
+        match name {
+            "0" => Some(String::from("vsh")),
+            "#" => Some(self.positional.len().to_string()),
+            "?" => Some(self.last_status.to_string()),
+            "!" => self.last_background.map(|pid| pid.to_string()),
+            "@" | "*" => Some(self.positional.join(" ")),
+            _ => match name.parse::<usize>() {
+                Ok(n) => n
+                    .checked_sub(1)
+                    .and_then(|i| self.positional.get(i))
+                    .cloned(),
+                Err(_) => env::var(name).ok(),
+            },
+        }
+    }
+
+    // What error messages start with, `vsh: file:line: ` inside a sourced file
+    pub fn error_prefix(&self) -> String {
+        match &self.location {
+            Some(location) => format!("vsh: {}: ", location),
+            None => String::from("vsh: "),
+        }
+    }
+
+    // Whether a config file changed since it was read
+    pub fn config_changed(&self) -> bool {
+        self.config_files
+            .iter()
+            .any(|(path, mtime)| fs::metadata(path).and_then(|meta| meta.modified()).ok() != *mtime)
+    }
+
+    // Reads the config files again and applies them, printing what is wrong with them
+    // Returns false when one has errors, the config in use is kept then
+    pub fn reload_config(&mut self) -> bool {
+        let text = fetch_data(&self.config_path);
+        let merged = load_config(
+            &self.config_path,
+            &text,
+            self.profile.as_deref(),
+            self.allow_insecure_config,
+        );
+        for (path, problem) in &merged.problems {
+            eprintln!("vsh: {}", problem.describe(path));
+        }
+        self.config_files = merged.files.clone();
+
+        match merged.config() {
+            Some(config) => {
+                self.apply_config(config);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Puts a new config in place of the current one; aliases, abbreviations and options
+    // only change where the config did, so those set at the prompt survive a reload
+    pub fn apply_config(&mut self, config: Config) {
+        for (name, value) in get_alias(&self.config) {
+            if self.aliases.get(&name) == Some(&value) {
+                self.aliases.remove(&name);
+            }
+        }
+        self.aliases.extend(get_alias(&config));
+
+        self.abbreviations.replace_config(
+            &self.config.abbr.clone().unwrap_or_default(),
+            &config.abbr.clone().unwrap_or_default(),
+        );
+
+        // An option the old config turned on goes back to off once the new one leaves it out
+        let old_options = self.config.options.clone().unwrap_or_default();
+        let new_options = config.options.clone().unwrap_or_default();
+        for (name, on) in &old_options {
+            if *on && !new_options.contains_key(name) && self.options.is_set(name) {
+                self.options.set(name, false).ok();
+            }
+        }
+        for (name, on) in &new_options {
+            if old_options.get(name) == Some(on) {
+                continue;
+            }
+            if let Err(e) = self.options.set(name, *on) {
+                eprintln!("vsh: config: {}", e);
+            }
+        }
+
+        apply_env(
+            &self.config.env.clone().unwrap_or_default(),
+            &config.env.clone().unwrap_or_default(),
+        );
+
+        self.command_not_found = config.command_not_found.clone().unwrap_or_default();
+        self.config = config;
+    }
+
+    // Every way `name` could be resolved, the one that wins comes first
+    pub fn resolve(&self, name: &str) -> Vec<Resolution> {
+        let mut found = Vec::new();
+        if let Some(alias) = self.aliases.get(name) {
+            found.push(Resolution::Alias(alias.to_string()));
+        }
+        if let Some(body) = self.functions.get(name) {
+            found.push(Resolution::Function(body.to_string()));
+        }
+        if builtins::lookup(name).is_some() {
+            found.push(Resolution::Builtin);
+        }
+        found.extend(find_in_path(name).into_iter().map(Resolution::File));
+        found
+    }
+}
@@ -10,6 +10,7 @@ use chrono::{DateTime, Datelike, Local, Timelike, Utc};
 
 pub trait ThemeContext {
     fn get_var(&self, var_name: &str) -> Option<&String>;
+    #[allow(clippy::wrong_self_convention)]
     fn from_sub_context<T: SubContext>(&mut self, contributor: &T);
     fn extend(&mut self, other: &Self);
 }
@@ -69,6 +70,17 @@ impl SubContext for DirectoryContext {
     }
 }
 
+///Environment variables, as `env.NAME`
+pub struct EnvironmentContext;
+
+impl SubContext for EnvironmentContext {
+    fn retrieve_var(&self) -> HashMap<String, String> {
+        std::env::vars()
+            .map(|(name, value)| (format!("env.{}", name), value))
+            .collect()
+    }
+}
+
 pub struct SessionContext {
     desktop_env: String,
     real_name: String,
@@ -77,6 +89,7 @@ pub struct SessionContext {
     hostname: String,
     platform: String,
     distribution: String,
+    ssh: bool,
 }
 
 impl SessionContext {
@@ -90,8 +103,19 @@ impl SessionContext {
             hostname: whoami::hostname(),
             platform: whoami::platform().to_string(),
             distribution: whoami::distro(),
+            ssh: std::env::var_os("SSH_CONNECTION").is_some()
+                || std::env::var_os("SSH_TTY").is_some(),
         }
     }
+
+    pub fn hostname(&self) -> &str {
+        &self.hostname
+    }
+
+    ///Whether the shell runs over SSH
+    pub fn ssh(&self) -> bool {
+        self.ssh
+    }
 }
 
 impl SubContext for SessionContext {
@@ -104,6 +128,7 @@ impl SubContext for SessionContext {
         vars.insert("hostname".to_string(), self.hostname.clone());
         vars.insert("platform".to_string(), self.platform.clone());
         vars.insert("distribution".to_string(), self.distribution.clone());
+        vars.insert("ssh".to_string(), self.ssh.to_string());
 
         vars
     }
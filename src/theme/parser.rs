@@ -33,7 +33,7 @@ fn from_hex(input: Span) -> Result<u8, std::num::ParseIntError> {
 }
 
 fn is_hex_digit(c: char) -> bool {
-    c.is_digit(16)
+    c.is_ascii_hexdigit()
 }
 
 fn hex_primary(input: Span) -> IResult<Span, u8> {
@@ -4,6 +4,7 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/.
  */
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod parser_test {
     use colored::Styles;
 
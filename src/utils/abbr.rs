@@ -0,0 +1,97 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use rustyline::{Cmd, ConditionalEventHandler, Event, EventContext, InputMode, RepeatCount};
+
+use crate::utils::command_position;
+
+// Where the abbreviation that ends at `pos` starts and what it expands to
+// Only a whole word in command position is expanded, so `git gco` stays as it is
+pub fn expand_abbreviation(
+    line: &str,
+    pos: usize,
+    abbreviations: &HashMap<String, String>,
+) -> Option<(usize, String)> {
+    let start = command_position(line, pos)?;
+    if line[pos..].starts_with(|c: char| !c.is_whitespace()) {
+        return None;
+    }
+    abbreviations
+        .get(&line[start..pos])
+        .map(|expansion| (start, expansion.clone()))
+}
+
+// Abbreviations from [abbr] and the `abbr` builtin, shared with the line editor
+#[derive(Clone, Default)]
+pub struct Abbreviations {
+    list: Arc<Mutex<HashMap<String, String>>>,
+}
+
+impl Abbreviations {
+    pub fn insert(&self, name: String, expansion: String) {
+        self.list.lock().unwrap().insert(name, expansion);
+    }
+
+    pub fn remove(&self, name: &str) -> Option<String> {
+        self.list.lock().unwrap().remove(name)
+    }
+
+    // Swaps the abbreviations of an old config for those of a new one,
+    // those added or changed with `abbr` since are kept
+    pub fn replace_config(&self, old: &HashMap<String, String>, new: &HashMap<String, String>) {
+        let mut list = self.list.lock().unwrap();
+        for (name, expansion) in old {
+            if list.get(name) == Some(expansion) {
+                list.remove(name);
+            }
+        }
+        list.extend(
+            new.iter()
+                .map(|(name, expansion)| (name.clone(), expansion.clone())),
+        );
+    }
+
+    // Every abbreviation, sorted by name
+    pub fn list(&self) -> Vec<(String, String)> {
+        let mut list = self
+            .list
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, expansion)| (name.clone(), expansion.clone()))
+            .collect::<Vec<_>>();
+        list.sort();
+        list
+    }
+
+    // Where the abbreviation that ends at `pos` starts and what it expands to
+    pub fn find(&self, line: &str, pos: usize) -> Option<(usize, String)> {
+        expand_abbreviation(line, pos, &self.list.lock().unwrap())
+    }
+
+    // Expands an abbreviation the line ends with, for lines accepted with enter
+    pub fn expand_line(&self, line: String) -> String {
+        let end = line.trim_end().len();
+        match self.find(&line, end) {
+            Some((start, expansion)) => format!("{}{}{}", &line[..start], expansion, &line[end..]),
+            None => line,
+        }
+    }
+}
+
+// Bound to space, an abbreviation before the cursor is handed to the completer,
+// which replaces it with the expansion and a space
+impl ConditionalEventHandler for Abbreviations {
+    fn handle(&self, _: &Event, n: RepeatCount, _: bool, ctx: &EventContext) -> Option<Cmd> {
+        if n != 1 || ctx.input_mode() == InputMode::Command {
+            return None;
+        }
+        self.find(ctx.line(), ctx.pos()).map(|_| Cmd::Complete)
+    }
+}
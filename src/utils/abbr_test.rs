@@ -0,0 +1,63 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::utils::*;
+
+    fn abbreviations() -> HashMap<String, String> {
+        [("gco", "git checkout"), ("l", "ls -la")]
+            .iter()
+            .map(|(name, expansion)| (name.to_string(), expansion.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn expand_abbreviation_test() {
+        let list = abbreviations();
+        assert_eq!(
+            expand_abbreviation("gco", 3, &list),
+            Some((0, "git checkout".to_string()))
+        );
+        assert_eq!(
+            expand_abbreviation("make && l", 9, &list),
+            Some((8, "ls -la".to_string()))
+        );
+        assert_eq!(expand_abbreviation("git gco", 7, &list), None);
+        assert_eq!(expand_abbreviation("gcom", 3, &list), None);
+        assert_eq!(expand_abbreviation("gc", 2, &list), None);
+    }
+
+    #[test]
+    fn expand_line_test() {
+        let list = Abbreviations::default();
+        list.replace_config(&HashMap::new(), &abbreviations());
+        assert_eq!(list.expand_line("gco".to_string()), "git checkout");
+        assert_eq!(list.expand_line("cd /; l ".to_string()), "cd /; ls -la ");
+        assert_eq!(list.expand_line("echo l".to_string()), "echo l");
+    }
+
+    #[test]
+    fn replace_config_test() {
+        let list = Abbreviations::default();
+        list.replace_config(&HashMap::new(), &abbreviations());
+        // Changed at the prompt, so a new config leaves it alone
+        list.insert("l".to_string(), "ls -l".to_string());
+
+        let new = [("gst".to_string(), "git status".to_string())]
+            .into_iter()
+            .collect();
+        list.replace_config(&abbreviations(), &new);
+        assert_eq!(
+            list.list(),
+            vec![
+                ("gst".to_string(), "git status".to_string()),
+                ("l".to_string(), "ls -l".to_string())
+            ]
+        );
+    }
+}
@@ -0,0 +1,166 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::BTreeMap;
+use std::env;
+
+use crate::utils::{home_dir, EnvConfig};
+
+// Whether the environment can hold a variable of this name
+pub fn valid_env_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(['=', '\0'])
+}
+
+// The environment cannot hold a NUL byte either, setting one would panic
+pub fn check_env_value(name: &str, value: &str) -> Result<(), String> {
+    if value.contains('\0') {
+        Err(format!("{}: cannot hold a NUL byte", name))
+    } else {
+        Ok(())
+    }
+}
+
+// Replaces `${NAME}` with what `lookup` finds for it, nothing when it finds nothing, and a `~`
+// starting the value or a `:` separated entry with `home`
+pub fn interpolate(
+    value: &str,
+    lookup: &mut dyn FnMut(&str) -> Option<String>,
+    home: Option<&str>,
+) -> String {
+    let mut result = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        match rest[start..].find('}') {
+            Some(end) => {
+                result.push_str(&rest[..start]);
+                result.push_str(&lookup(&rest[start + 2..start + end]).unwrap_or_default());
+                rest = &rest[start + end + 1..];
+            }
+            None => break,
+        }
+    }
+    result.push_str(rest);
+
+    let home = match home {
+        Some(home) => home,
+        None => return result,
+    };
+    result
+        .split(':')
+        .map(|entry| match entry.strip_prefix('~') {
+            Some(after) if after.is_empty() || after.starts_with('/') => {
+                format!("{}{}", home, after)
+            }
+            _ => entry.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+// The values of an [env] table with every `${NAME}` filled in, a name from the table gets its
+// value from the table and any other one comes from `environment`
+pub fn resolve_env(
+    vars: &BTreeMap<String, String>,
+    environment: &dyn Fn(&str) -> Option<String>,
+    home: Option<&str>,
+) -> BTreeMap<String, String> {
+    fn resolve(
+        name: &str,
+        vars: &BTreeMap<String, String>,
+        environment: &dyn Fn(&str) -> Option<String>,
+        home: Option<&str>,
+        stack: &mut Vec<String>,
+    ) -> Option<String> {
+        // `PATH = "${PATH}:..."` refers to the variable from before, as does a cycle
+        let value = match vars.get(name) {
+            Some(value) if !stack.iter().any(|seen| seen == name) => value,
+            _ => return environment(name),
+        };
+        stack.push(name.to_string());
+        let mut lookup = |inner: &str| resolve(inner, vars, environment, home, stack);
+        let value = interpolate(value, &mut lookup, home);
+        stack.pop();
+        Some(value)
+    }
+
+    vars.keys()
+        .map(|name| {
+            let value = resolve(name, vars, environment, home, &mut Vec::new());
+            (name.clone(), value.unwrap_or_default())
+        })
+        .collect()
+}
+
+// `prepend`, `path` and `append` as one `:` separated list, an entry only stays where it
+// first appears
+pub fn join_path(prepend: &[String], path: &str, append: &[String]) -> String {
+    let mut entries: Vec<&str> = Vec::new();
+    for entry in prepend
+        .iter()
+        .map(String::as_str)
+        .chain(path.split(':'))
+        .chain(append.iter().map(String::as_str))
+    {
+        if !entry.is_empty() && !entries.contains(&entry) {
+            entries.push(entry);
+        }
+    }
+    entries.join(":")
+}
+
+// Sets what changed in [env] from `old` to `new`; a variable that left the table is unset
+// unless it was changed since
+pub fn apply_env(old: &EnvConfig, new: &EnvConfig) {
+    let home = home_dir().map(|home| home.to_string_lossy().to_string());
+    let environment = |name: &str| env::var(name).ok();
+    let old_values = resolve_env(&old.vars, &environment, home.as_deref());
+    let new_values = resolve_env(&new.vars, &environment, home.as_deref());
+
+    for (name, value) in &old_values {
+        if valid_env_name(name)
+            && !new.vars.contains_key(name)
+            && env::var(name).ok().as_ref() == Some(value)
+        {
+            env::remove_var(name);
+        }
+    }
+    // validate_config reports the names and values that cannot be set
+    for (name, value) in &new_values {
+        if !valid_env_name(name) || value.contains('\0') {
+            continue;
+        }
+        if old.vars.get(name) != new.vars.get(name) {
+            env::set_var(name, value);
+        }
+    }
+
+    if old.path_prepend != new.path_prepend
+        || old.path_append != new.path_append
+        || old.vars.get("PATH") != new.vars.get("PATH")
+    {
+        let expand = |list: &Option<Vec<String>>| {
+            list.iter()
+                .flatten()
+                .map(|entry| interpolate(entry, &mut |name| environment(name), home.as_deref()))
+                .collect::<Vec<_>>()
+        };
+        // The entries the old lists added go first, so one dropped from a list leaves PATH
+        let added = expand(&old.path_prepend)
+            .into_iter()
+            .chain(expand(&old.path_append))
+            .collect::<Vec<_>>();
+        let path = env::var("PATH").unwrap_or_default();
+        let path = path
+            .split(':')
+            .filter(|entry| !added.iter().any(|added| added == entry))
+            .collect::<Vec<_>>()
+            .join(":");
+        env::set_var(
+            "PATH",
+            join_path(&expand(&new.path_prepend), &path, &expand(&new.path_append)),
+        );
+    }
+}
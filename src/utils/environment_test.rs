@@ -0,0 +1,113 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::utils::*;
+
+    fn environment(name: &str) -> Option<String> {
+        match name {
+            "PATH" => Some("/bin".to_string()),
+            "USER" => Some("linus".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn interpolate_test() {
+        let mut lookup = |name: &str| environment(name);
+        assert_eq!(
+            interpolate("${USER}-${NOPE}x", &mut lookup, Some("/home/linus")),
+            "linus-x"
+        );
+        assert_eq!(
+            interpolate("~/bin:~:a~/b:~x", &mut lookup, Some("/home/linus")),
+            "/home/linus/bin:/home/linus:a~/b:~x"
+        );
+        assert_eq!(interpolate("~/bin", &mut lookup, None), "~/bin");
+        assert_eq!(interpolate("${USER", &mut lookup, None), "${USER");
+    }
+
+    #[test]
+    fn resolve_env_test() {
+        let vars = [
+            ("PATH", "${GOPATH}/bin:${PATH}"),
+            ("GOPATH", "~/go"),
+            ("A", "${B}"),
+            ("B", "${A}"),
+        ]
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect::<BTreeMap<_, _>>();
+
+        let values = resolve_env(&vars, &environment, Some("/home/linus"));
+        assert_eq!(values["GOPATH"], "/home/linus/go");
+        assert_eq!(values["PATH"], "/home/linus/go/bin:/bin");
+        // A cycle ends at the environment, which has neither
+        assert_eq!(values["A"], "");
+    }
+
+    #[test]
+    fn join_path_test() {
+        let list = |entries: &[&str]| entries.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            join_path(
+                &list(&["/a", "/b"]),
+                "/b:/usr/bin::/a",
+                &list(&["/usr/bin", "/c"])
+            ),
+            "/a:/b:/usr/bin:/c"
+        );
+        assert_eq!(join_path(&[], "", &[]), "");
+    }
+
+    #[test]
+    fn apply_env_test() {
+        let new = EnvConfig {
+            vars: [("A=B", "x"), ("", "x"), ("VSH_APPLY_ENV_TEST", "set")]
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            ..EnvConfig::default()
+        };
+        // Names the environment cannot hold are skipped instead of panicking
+        apply_env(&EnvConfig::default(), &new);
+        assert_eq!(std::env::var("VSH_APPLY_ENV_TEST").unwrap(), "set");
+        apply_env(&new, &EnvConfig::default());
+        assert!(std::env::var("VSH_APPLY_ENV_TEST").is_err());
+    }
+
+    #[test]
+    fn apply_path_test() {
+        let saved = std::env::var("PATH").unwrap();
+        let list = |entries: &[&str]| Some(entries.iter().map(|e| e.to_string()).collect());
+        let old = EnvConfig {
+            path_prepend: list(&["/vsh-test/a", "/vsh-test/b"]),
+            path_append: list(&["/vsh-test/c"]),
+            ..EnvConfig::default()
+        };
+        apply_env(&EnvConfig::default(), &old);
+        assert_eq!(
+            std::env::var("PATH").unwrap(),
+            format!("/vsh-test/a:/vsh-test/b:{}:/vsh-test/c", saved)
+        );
+
+        // A reload leaves out what the lists no longer have instead of piling entries up
+        let new = EnvConfig {
+            path_prepend: list(&["/vsh-test/b"]),
+            ..EnvConfig::default()
+        };
+        apply_env(&old, &new);
+        assert_eq!(
+            std::env::var("PATH").unwrap(),
+            format!("/vsh-test/b:{}", saved)
+        );
+        apply_env(&new, &EnvConfig::default());
+        assert_eq!(std::env::var("PATH").unwrap(), saved);
+        std::env::set_var("PATH", saved);
+    }
+}
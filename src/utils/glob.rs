@@ -0,0 +1,133 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use std::fs;
+
+// True if the pattern has an unescaped `*`, `?` or `[`
+pub fn has_glob(pattern: &str) -> bool {
+    let mut escaped = false;
+    for c in pattern.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '*' | '?' | '[' => return true,
+            _ => (),
+        }
+    }
+    false
+}
+
+fn unescape(pattern: &str) -> String {
+    let mut out = String::new();
+    let mut escaped = false;
+    for c in pattern.chars() {
+        if c == '\\' && !escaped {
+            escaped = true;
+            continue;
+        }
+        escaped = false;
+        out.push(c);
+    }
+    out
+}
+
+// Matches `[abc]`, `[a-z]` and `[!abc]` at the start of `pattern`,
+// returns whether `c` is in the set and how long the bracket expression was
+fn match_bracket(pattern: &[char], c: char) -> Option<(bool, usize)> {
+    let mut i = 1;
+    let negated = matches!(pattern.get(i), Some('!') | Some('^'));
+    if negated {
+        i += 1;
+    }
+
+    let mut found = false;
+    let mut first = true;
+    loop {
+        let start = *pattern.get(i)?;
+        if start == ']' && !first {
+            return Some((found != negated, i + 1));
+        }
+        first = false;
+        if pattern.get(i + 1) == Some(&'-') && pattern.get(i + 2).is_some_and(|c| *c != ']') {
+            let end = pattern[i + 2];
+            found |= start <= c && c <= end;
+            i += 3;
+        } else {
+            found |= start == c;
+            i += 1;
+        }
+    }
+}
+
+fn match_chars(pattern: &[char], name: &[char]) -> bool {
+    match pattern.first() {
+        None => name.is_empty(),
+        Some('*') => (0..=name.len()).any(|skip| match_chars(&pattern[1..], &name[skip..])),
+        Some('?') => !name.is_empty() && match_chars(&pattern[1..], &name[1..]),
+        Some('[') if !name.is_empty() => match match_bracket(pattern, name[0]) {
+            Some((true, len)) => match_chars(&pattern[len..], &name[1..]),
+            Some((false, _)) => false,
+            // An unclosed `[` is just a bracket
+            None => name[0] == '[' && match_chars(&pattern[1..], &name[1..]),
+        },
+        Some('\\') if pattern.len() > 1 => {
+            name.first() == Some(&pattern[1]) && match_chars(&pattern[2..], &name[1..])
+        }
+        Some(c) => name.first() == Some(c) && match_chars(&pattern[1..], &name[1..]),
+    }
+}
+
+// Matches a single file name against a pattern with `*`, `?` and `[...]`
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+    match_chars(&pattern, &name)
+}
+
+// Every path matching the pattern, sorted, or nothing if there is no match
+// Hidden files only match a pattern that starts with a dot, unless `dotglob` is set
+pub fn glob(pattern: &str, dotglob: bool) -> Vec<String> {
+    let mut paths = vec![String::new()];
+
+    for (i, part) in pattern.split('/').enumerate() {
+        let separator = if i == 0 { "" } else { "/" };
+        if !has_glob(part) {
+            let literal = unescape(part);
+            for path in paths.iter_mut() {
+                path.push_str(separator);
+                path.push_str(&literal);
+            }
+            continue;
+        }
+
+        let mut matches = Vec::new();
+        for path in paths {
+            let dir = match (i, path.as_str()) {
+                (0, _) => ".",
+                (_, "") => "/",
+                (_, dir) => dir,
+            };
+            let entries = match fs::read_dir(dir) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if name.starts_with('.') && !part.starts_with('.') && !dotglob {
+                    continue;
+                }
+                if glob_match(part, &name) {
+                    matches.push(format!("{}{}{}", path, separator, name));
+                }
+            }
+        }
+        paths = matches;
+    }
+
+    paths.retain(|path| fs::symlink_metadata(path).is_ok());
+    paths.sort();
+    paths
+}
@@ -0,0 +1,55 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::utils::*;
+
+    #[test]
+    fn glob_match_test() {
+        assert!(glob_match("*.rs", "main.rs"));
+        assert!(!glob_match("*.rs", "main.rs.bak"));
+        assert!(glob_match("?ain.*", "main.rs"));
+        assert!(glob_match("[a-c]at", "bat"));
+        assert!(!glob_match("[!a-c]at", "bat"));
+        assert!(glob_match("[]]", "]"));
+        assert!(glob_match("a\\*", "a*"));
+        assert!(!glob_match("a\\*", "ab"));
+        assert!(glob_match("[oops", "[oops"));
+    }
+
+    #[test]
+    fn has_glob_test() {
+        assert!(has_glob("*.rs"));
+        assert!(has_glob("file[12]"));
+        assert!(!has_glob("plain"));
+        assert!(!has_glob("escaped\\*"));
+    }
+
+    #[test]
+    fn glob_test() {
+        let dir = std::env::temp_dir().join(format!("vsh-glob-{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        for name in ["b.txt", "a.txt", ".hidden.txt", "sub/c.txt"] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+        let root = dir.to_string_lossy();
+
+        assert_eq!(
+            glob(&format!("{}/*.txt", root), false),
+            vec![format!("{}/a.txt", root), format!("{}/b.txt", root)]
+        );
+        assert_eq!(glob(&format!("{}/*.txt", root), true).len(), 3);
+        assert_eq!(
+            glob(&format!("{}/*/c.txt", root), false),
+            vec![format!("{}/sub/c.txt", root)]
+        );
+        assert!(glob(&format!("{}/*.nothing", root), false).is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -6,7 +6,7 @@
 
 use std::borrow::Cow::{self, Borrowed, Owned};
 
-use crate::utils::Config;
+use crate::utils::{path_executables, Abbreviations, Config};
 
 use colored::*;
 
@@ -28,6 +28,23 @@ pub struct PromptEffects {
     pub hinter: HistoryHinter,
     pub colored_prompt: String,
     pub ctx: Config,
+    // Builtins, aliases and functions, completed in command position along with $PATH
+    pub commands: Vec<String>,
+    // With autocd directories are completed in command position too
+    pub autocd: bool,
+    pub abbreviations: Abbreviations,
+}
+
+// Where the word under the cursor starts, if that word is the name of a command
+pub fn command_position(line: &str, pos: usize) -> Option<usize> {
+    let before = &line[..pos];
+    let start = before
+        .rfind(|c: char| c.is_whitespace() || ";|&{".contains(c))
+        .map_or(0, |i| i + 1);
+    match before[..start].trim_end().chars().last() {
+        None | Some(';') | Some('|') | Some('&') | Some('{') => Some(start),
+        _ => None,
+    }
 }
 
 // For tab completion, not fully customizable yet
@@ -40,7 +57,48 @@ impl Completer for PromptEffects {
         pos: usize,
         ctx: &Context<'_>,
     ) -> Result<(usize, Vec<Pair>), ReadlineError> {
-        self.completer.complete(line, pos, ctx)
+        // Space asks for this too, see `Abbreviations`
+        if let Some((start, expansion)) = self.abbreviations.find(line, pos) {
+            let pair = Pair {
+                display: expansion.clone(),
+                replacement: format!("{} ", expansion),
+            };
+            return Ok((start, vec![pair]));
+        }
+
+        let start = match command_position(line, pos) {
+            Some(start) if !line[start..pos].contains('/') => start,
+            // Paths like `./run.sh` and arguments complete as file names
+            _ => return self.completer.complete(line, pos, ctx),
+        };
+
+        let word = &line[start..pos];
+        let mut names = self
+            .commands
+            .iter()
+            .cloned()
+            .chain(path_executables())
+            .filter(|name| name.starts_with(word))
+            .collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+        let mut pairs = names
+            .into_iter()
+            .map(|name| Pair {
+                display: name.clone(),
+                replacement: format!("{} ", name),
+            })
+            .collect::<Vec<_>>();
+
+        if self.autocd {
+            let (_, files) = self.completer.complete(line, pos, ctx)?;
+            pairs.extend(
+                files
+                    .into_iter()
+                    .filter(|pair| pair.replacement.ends_with('/')),
+            );
+        }
+        Ok((start, pairs))
     }
 }
 
@@ -88,7 +146,7 @@ impl Highlighter for PromptEffects {
                     "purple" => estr = estr.purple(),
                     "cyan" => estr = estr.cyan(),
                     "white" => estr = estr.white(),
-                    "red" | _ => estr = estr.red(),
+                    _ => estr = estr.red(),
                 }
             }
 
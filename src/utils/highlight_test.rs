@@ -0,0 +1,19 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+#[cfg(test)]
+mod tests {
+    use crate::utils::*;
+
+    #[test]
+    fn command_position_test() {
+        assert_eq!(command_position("gi", 2), Some(0));
+        assert_eq!(command_position("  src", 5), Some(2));
+        assert_eq!(command_position("ls sr", 5), None);
+        assert_eq!(command_position("ls | gr", 7), Some(5));
+        assert_eq!(command_position("a;b", 3), Some(2));
+        assert_eq!(command_position("true && ca", 10), Some(8));
+    }
+}
@@ -0,0 +1,372 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use toml::value::Table;
+use toml::Value;
+
+use crate::theme::context::SessionContext;
+use crate::utils::{
+    check_permissions, config_dir, did_you_mean, glob_match, home_dir, interpolate,
+    legacy_config_path, locate, os_error_text, validate_config, Config, Problem,
+};
+
+// config.toml with its includes and the conf.d files merged into one table
+#[derive(Default)]
+pub struct MergedConfig {
+    pub table: Table,
+    // The file each value was last set by, keyed like `prompt.theme`
+    pub origins: BTreeMap<String, PathBuf>,
+    // Every file that was read, and conf.d itself, with its modification time
+    pub files: Vec<(PathBuf, Option<SystemTime>)>,
+    pub problems: Vec<(PathBuf, Problem)>,
+    // The `[profile.<name>]` tables laid over the rest, in order
+    pub profiles: Vec<String>,
+}
+
+impl MergedConfig {
+    // The merged config, unless a file has an error
+    pub fn config(&self) -> Option<Config> {
+        if self.problems.iter().any(|(_, problem)| problem.error) {
+            return None;
+        }
+        Value::Table(self.table.clone()).try_into().ok()
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+// Merges `from` into `into`, tables key by key and anything else but the alias list replaced
+// as a whole
+// `origin` names the file a value like `prompt.theme` comes from
+pub fn merge_tables(
+    into: &mut Table,
+    from: Table,
+    origin: &dyn Fn(&str) -> PathBuf,
+    prefix: &str,
+    origins: &mut BTreeMap<String, PathBuf>,
+) {
+    for (key, value) in from {
+        let name = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+        match (into.get_mut(&key), value) {
+            (Some(Value::Table(old)), Value::Table(new)) => {
+                merge_tables(old, new, origin, &name, origins);
+            }
+            // Alias lists are joined so shared aliases survive, a later alias of the same
+            // name wins
+            (Some(Value::Array(old)), Value::Array(new)) if name == "misc.alias" => {
+                let first = |entry: &Value| entry.get(0).cloned();
+                old.retain(|entry| !new.iter().any(|added| first(added) == first(entry)));
+                old.extend(new);
+                origins.insert(name.clone(), origin(&name));
+            }
+            (_, value) => {
+                let inside = format!("{}.", name);
+                origins.retain(|key, _| !key.starts_with(&inside));
+                if let Value::Table(table) = value {
+                    let mut merged = Table::new();
+                    merge_tables(&mut merged, table, origin, &name, origins);
+                    into.insert(key, Value::Table(merged));
+                } else {
+                    origins.insert(name.clone(), origin(&name));
+                    into.insert(key, value);
+                }
+            }
+        }
+    }
+}
+
+// Where an `include` entry points: `~` and `${VAR}` are expanded and a relative path is
+// taken from the directory of the file including it
+fn include_path(entry: &str, from: &Path) -> PathBuf {
+    let home = home_dir().map(|home| home.to_string_lossy().to_string());
+    let entry = interpolate(entry, &mut |name| env::var(name).ok(), home.as_deref());
+    match from.parent() {
+        Some(dir) => dir.join(entry),
+        None => PathBuf::from(entry),
+    }
+}
+
+// Merges a file that has been read, after the files it includes so it wins over them
+fn merge_file(
+    merged: &mut MergedConfig,
+    path: &Path,
+    text: &str,
+    stack: &mut Vec<PathBuf>,
+    allow_insecure: bool,
+) {
+    if let Some(problem) = check_permissions(path, allow_insecure) {
+        merged.problems.push((path.to_path_buf(), problem));
+        if !allow_insecure {
+            merged.files.push((path.to_path_buf(), modified(path)));
+            return;
+        }
+    }
+    let problems = validate_config(text);
+    let broken = problems.iter().any(|problem| problem.error);
+    merged.problems.extend(
+        problems
+            .into_iter()
+            .map(|problem| (path.to_path_buf(), problem)),
+    );
+    if broken {
+        merged.files.push((path.to_path_buf(), modified(path)));
+        return;
+    }
+
+    let mut table = match toml::from_str::<Value>(text) {
+        Ok(Value::Table(table)) => table,
+        _ => return,
+    };
+    stack.push(path.to_path_buf());
+    if let Some(Value::Array(includes)) = table.remove("include") {
+        for entry in includes.iter().filter_map(Value::as_str) {
+            let include = include_path(entry, path);
+            if stack.contains(&include) {
+                merged.problems.push((
+                    path.to_path_buf(),
+                    Problem::warning(
+                        locate(text, None, "include"),
+                        format!("include `{}`: already being included", entry),
+                    ),
+                ));
+                continue;
+            }
+            match fs::read_to_string(&include) {
+                Ok(text) => merge_file(merged, &include, &text, stack, allow_insecure),
+                Err(e) => {
+                    // Watched anyway, so creating the file later is noticed
+                    merged.files.push((include.clone(), None));
+                    merged.problems.push((
+                        path.to_path_buf(),
+                        Problem::warning(
+                            locate(text, None, "include"),
+                            format!("include `{}`: {}", entry, os_error_text(&e)),
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+    stack.pop();
+
+    merged.files.push((path.to_path_buf(), modified(path)));
+    let origin = |_: &str| path.to_path_buf();
+    merge_tables(&mut merged.table, table, &origin, "", &mut merged.origins);
+}
+
+// Whether a profile's `match` rule fits this session, every test in it has to
+pub fn profile_matches(rule: &Value, hostname: &str, ssh: bool) -> bool {
+    let rule = match rule.as_table() {
+        Some(rule) if !rule.is_empty() => rule,
+        _ => return false,
+    };
+    rule.iter().all(|(key, value)| match (key.as_str(), value) {
+        ("hostname", Value::String(pattern)) => glob_match(pattern, hostname),
+        ("ssh", Value::Boolean(wanted)) => *wanted == ssh,
+        _ => false,
+    })
+}
+
+// Lays the profiles for this session over the config: the one `requested`, otherwise every
+// one whose `match` rule fits, in name order so later ones win
+pub fn apply_profiles(
+    merged: &mut MergedConfig,
+    requested: Option<&str>,
+    hostname: &str,
+    ssh: bool,
+    path: &Path,
+) {
+    let profiles = match merged.table.remove("profile") {
+        Some(Value::Table(profiles)) => profiles,
+        _ => Table::new(),
+    };
+    let chosen = match requested {
+        Some(name) if profiles.contains_key(name) => vec![name.to_string()],
+        Some(name) => {
+            let names = profiles.keys().map(String::as_str).collect::<Vec<_>>();
+            merged.problems.push((
+                path.to_path_buf(),
+                Problem::warning(
+                    None,
+                    format!("profile `{}` not found{}", name, did_you_mean(name, &names)),
+                ),
+            ));
+            Vec::new()
+        }
+        None => profiles
+            .iter()
+            .filter(|(_, profile)| {
+                profile
+                    .get("match")
+                    .is_some_and(|rule| profile_matches(rule, hostname, ssh))
+            })
+            .map(|(name, _)| name.clone())
+            .collect(),
+    };
+
+    let declared = merged.origins.clone();
+    for name in chosen {
+        let mut table = match profiles.get(&name) {
+            Some(Value::Table(table)) => table.clone(),
+            _ => continue,
+        };
+        table.remove("match");
+        let origin = |key: &str| {
+            declared
+                .get(&format!("profile.{}.{}", name, key))
+                .cloned()
+                .unwrap_or_else(|| path.to_path_buf())
+        };
+        merge_tables(&mut merged.table, table, &origin, "", &mut merged.origins);
+        merged.profiles.push(name);
+    }
+    merged.origins.retain(|key, _| !key.starts_with("profile."));
+}
+
+// Merges the config file at `path`, whose `text` was already read, with the files it includes
+// and then the `conf.d/*.toml` files next to it in lexical order, later files win, and lays
+// the profiles for this session over the result
+// Files others can change are left out unless `allow_insecure`
+pub fn load_config(
+    path: &Path,
+    text: &str,
+    profile: Option<&str>,
+    allow_insecure: bool,
+) -> MergedConfig {
+    let mut merged = MergedConfig::default();
+    merge_file(&mut merged, path, text, &mut Vec::new(), allow_insecure);
+
+    // The legacy ~/.vshrc.toml still goes with the conf.d of the config directory
+    let conf_d = if legacy_config_path().as_deref() == Some(path) {
+        config_dir().join("conf.d")
+    } else {
+        path.with_file_name("conf.d")
+    };
+    merged.files.push((conf_d.clone(), modified(&conf_d)));
+    let mut drop_ins = fs::read_dir(&conf_d)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        .collect::<Vec<_>>();
+    drop_ins.sort();
+    for drop_in in drop_ins {
+        match fs::read_to_string(&drop_in) {
+            Ok(text) => merge_file(
+                &mut merged,
+                &drop_in,
+                &text,
+                &mut Vec::new(),
+                allow_insecure,
+            ),
+            Err(e) => merged.problems.push((
+                drop_in,
+                Problem {
+                    error: true,
+                    position: None,
+                    message: os_error_text(&e),
+                },
+            )),
+        }
+    }
+
+    let session = SessionContext::new();
+    apply_profiles(
+        &mut merged,
+        profile,
+        session.hostname(),
+        session.ssh(),
+        path,
+    );
+
+    if merged.problems.iter().all(|(_, problem)| !problem.error) {
+        if let Err(e) = Value::Table(merged.table.clone()).try_into::<Config>() {
+            merged.problems.push((
+                path.to_path_buf(),
+                Problem {
+                    error: true,
+                    position: None,
+                    message: e.to_string(),
+                },
+            ));
+        }
+    }
+    merged
+}
+
+fn toml_key(key: &str) -> String {
+    let bare = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if bare {
+        key.to_string()
+    } else {
+        Value::String(key.to_string()).to_string()
+    }
+}
+
+// `prefix` is the dotted name values are found under in `origins`, `header` the same name as
+// a TOML table header
+fn describe_table(
+    table: &Table,
+    prefix: &str,
+    header: &str,
+    merged: &MergedConfig,
+    out: &mut Vec<String>,
+) {
+    let join = |start: &str, key: &str| {
+        if start.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", start, key)
+        }
+    };
+    for (key, value) in table.iter().filter(|(_, value)| !value.is_table()) {
+        let origin = merged
+            .origins
+            .get(&join(prefix, key))
+            .map(|path| format!("  # {}", path.display()))
+            .unwrap_or_default();
+        out.push(format!("{} = {}{}", toml_key(key), value, origin));
+    }
+    for (key, value) in table.iter() {
+        if let Value::Table(inner) = value {
+            let header = join(header, &toml_key(key));
+            out.push(String::new());
+            out.push(format!("[{}]", header));
+            describe_table(inner, &join(prefix, key), &header, merged, out);
+        }
+    }
+}
+
+// The merged config as TOML, every value commented with the file it came from
+pub fn describe_config(merged: &MergedConfig) -> String {
+    let mut out = vec!["# Files merged, later ones win:".to_string()];
+    for (path, modified) in &merged.files {
+        if modified.is_some() && !path.is_dir() {
+            out.push(format!("#   {}", path.display()));
+        }
+    }
+    if !merged.profiles.is_empty() {
+        out.push(format!("# Profiles: {}", merged.profiles.join(", ")));
+    }
+    describe_table(&merged.table, "", "", merged, &mut out);
+    out.join("\n")
+}
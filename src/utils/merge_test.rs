@@ -0,0 +1,146 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use toml::value::Table;
+
+    use crate::utils::*;
+
+    fn table(text: &str) -> Table {
+        toml::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn merge_tables_test() {
+        let mut merged = Table::new();
+        let mut origins = BTreeMap::new();
+        let team = Path::new("team.toml");
+        let mine = Path::new("config.toml");
+        merge_tables(
+            &mut merged,
+            table(
+                "[prompt]\ntheme = 'a'\n[effects]\nbold = true\n[abbr]\ng = 'git'
+                [misc]\nalias = [['l', 'ls'], ['g', 'git']]",
+            ),
+            &|_| team.to_path_buf(),
+            "",
+            &mut origins,
+        );
+        merge_tables(
+            &mut merged,
+            table(
+                "[effects]\nbold = false\n[abbr]\ngs = 'git status'
+                [misc]\nalias = [['g', 'grep']]",
+            ),
+            &|_| mine.to_path_buf(),
+            "",
+            &mut origins,
+        );
+
+        let expected = "[prompt]\ntheme = 'a'\n[effects]\nbold = false
+            [abbr]\ng = 'git'\ngs = 'git status'
+            [misc]\nalias = [['l', 'ls'], ['g', 'grep']]";
+        assert_eq!(merged, table(expected));
+        assert_eq!(origins["prompt.theme"], team);
+        assert_eq!(origins["effects.bold"], mine);
+        assert_eq!(origins["abbr.g"], team);
+    }
+
+    #[test]
+    fn load_config_test() {
+        let dir = std::env::temp_dir().join(format!("vsh-merge-{}", std::process::id()));
+        fs::create_dir_all(dir.join("conf.d")).unwrap();
+        fs::write(
+            dir.join("team.toml"),
+            "[prompt]\ntheme = 'team'\n[abbr]\ng = 'git'",
+        )
+        .unwrap();
+        fs::write(dir.join("conf.d/b.toml"), "[abbr]\ng = 'b'").unwrap();
+        fs::write(dir.join("conf.d/a.toml"), "[abbr]\ng = 'a'\nx = 'a'").unwrap();
+        fs::write(dir.join("conf.d/notes.txt"), "not toml").unwrap();
+        let path = dir.join("config.toml");
+        let text = "include = ['team.toml', 'missing.toml']\n[prompt]\ntheme = 'mine'";
+
+        let merged = load_config(&path, text, None, false);
+        let abbr = merged.config().unwrap().abbr.unwrap();
+        assert_eq!(abbr["g"], "b");
+        assert_eq!(abbr["x"], "a");
+        assert_eq!(merged.origins["prompt.theme"], path);
+        assert_eq!(merged.origins["abbr.g"], dir.join("conf.d/b.toml"));
+        // The missing include is only a warning
+        assert_eq!(merged.problems.len(), 1);
+        assert!(!merged.problems[0].1.error);
+        let files = merged
+            .files
+            .iter()
+            .map(|(file, _)| file.clone())
+            .collect::<Vec<_>>();
+        assert!(files.contains(&dir.join("missing.toml")));
+        assert!(files.contains(&dir.join("conf.d")));
+
+        let described = describe_config(&merged);
+        assert!(described.contains(&format!("theme = \"mine\"  # {}", path.display())));
+
+        let merged = load_config(&path, "include = 'team.toml'", None, false);
+        assert!(merged.config().is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn apply_profiles_test() {
+        let rule = |text: &str| toml::from_str::<toml::Value>(text).unwrap();
+        assert!(profile_matches(&rule("hostname = 'dev-*'"), "dev-1", false));
+        assert!(!profile_matches(
+            &rule("hostname = 'dev-*'\nssh = true"),
+            "dev-1",
+            false
+        ));
+        assert!(profile_matches(&rule("ssh = false"), "home", false));
+        assert!(!profile_matches(&rule(""), "home", false));
+
+        let path = Path::new("config.toml");
+        let text = "[prompt]\ntheme = 'base'
+            [profile.work.match]\nhostname = '*.corp'
+            [profile.work.prompt]\ntheme = 'work'
+            [profile.remote]\nmatch = { ssh = true }\nabbr = { s = 'ssh' }";
+        let load = || {
+            let mut merged = MergedConfig {
+                table: table(text),
+                ..MergedConfig::default()
+            };
+            merged.origins.insert(
+                "profile.work.prompt.theme".to_string(),
+                PathBuf::from("work.toml"),
+            );
+            merged
+        };
+
+        let mut merged = load();
+        apply_profiles(&mut merged, None, "box.corp", true, path);
+        assert_eq!(merged.profiles, vec!["remote", "work"]);
+        assert_eq!(merged.table["prompt"]["theme"].as_str(), Some("work"));
+        assert_eq!(merged.origins["prompt.theme"], PathBuf::from("work.toml"));
+        assert_eq!(merged.origins["abbr.s"], path);
+        assert!(!merged.table.contains_key("profile"));
+
+        let mut merged = load();
+        apply_profiles(&mut merged, Some("remote"), "box.corp", false, path);
+        assert_eq!(merged.profiles, vec!["remote"]);
+        assert_eq!(merged.table["prompt"]["theme"].as_str(), Some("base"));
+
+        let mut merged = load();
+        apply_profiles(&mut merged, Some("wrok"), "box.corp", false, path);
+        assert!(merged.profiles.is_empty());
+        assert!(merged.problems[0]
+            .1
+            .message
+            .contains("did you mean `work`?"));
+    }
+}
@@ -4,7 +4,40 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/.
 */
 
+#[cfg(test)]
+pub mod abbr_test;
+#[cfg(test)]
+pub mod environment_test;
+#[cfg(test)]
+pub mod glob_test;
+#[cfg(test)]
+pub mod highlight_test;
+#[cfg(test)]
+pub mod merge_test;
+#[cfg(test)]
+pub mod signals_test;
+#[cfg(test)]
+pub mod suggest_test;
+#[cfg(test)]
+pub mod time_test;
+#[cfg(test)]
+pub mod utils_test;
+#[cfg(test)]
+pub mod validate_test;
+
+mod abbr;
+mod environment;
+mod glob;
 mod highlight;
+mod merge;
+mod signals;
+mod suggest;
+mod time;
+#[allow(clippy::module_inception)]
 mod utils;
+mod validate;
 
-pub use {highlight::*, utils::*};
+pub use {
+    abbr::*, environment::*, glob::*, highlight::*, merge::*, signals::*, suggest::*, time::*,
+    utils::*, validate::*,
+};
@@ -0,0 +1,115 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+use libc::c_int;
+
+// Signal names as the user writes them, numbers come from libc so they match the platform
+const SIGNALS: &[(&str, c_int)] = &[
+    ("HUP", libc::SIGHUP),
+    ("INT", libc::SIGINT),
+    ("QUIT", libc::SIGQUIT),
+    ("ILL", libc::SIGILL),
+    ("TRAP", libc::SIGTRAP),
+    ("ABRT", libc::SIGABRT),
+    ("BUS", libc::SIGBUS),
+    ("FPE", libc::SIGFPE),
+    ("KILL", libc::SIGKILL),
+    ("USR1", libc::SIGUSR1),
+    ("SEGV", libc::SIGSEGV),
+    ("USR2", libc::SIGUSR2),
+    ("PIPE", libc::SIGPIPE),
+    ("ALRM", libc::SIGALRM),
+    ("TERM", libc::SIGTERM),
+    ("CHLD", libc::SIGCHLD),
+    ("CONT", libc::SIGCONT),
+    ("STOP", libc::SIGSTOP),
+    ("TSTP", libc::SIGTSTP),
+    ("TTIN", libc::SIGTTIN),
+    ("TTOU", libc::SIGTTOU),
+    ("URG", libc::SIGURG),
+    ("XCPU", libc::SIGXCPU),
+    ("XFSZ", libc::SIGXFSZ),
+    ("VTALRM", libc::SIGVTALRM),
+    ("PROF", libc::SIGPROF),
+    ("WINCH", libc::SIGWINCH),
+    ("IO", libc::SIGIO),
+    ("SYS", libc::SIGSYS),
+];
+
+// Signals vsh listens for, and so the only ones `trap` can hook into
+pub const TRAPPABLE: &[c_int] = &[
+    libc::SIGHUP,
+    libc::SIGINT,
+    libc::SIGQUIT,
+    libc::SIGUSR1,
+    libc::SIGUSR2,
+    libc::SIGALRM,
+    libc::SIGTERM,
+    libc::SIGTSTP,
+    libc::SIGWINCH,
+];
+
+// Every known signal, ordered by number
+pub fn signal_list() -> Vec<(&'static str, c_int)> {
+    let mut list = SIGNALS.to_vec();
+    list.sort_by_key(|(_, number)| *number);
+    list
+}
+
+// Accepts `INT`, `SIGINT`, `int` or `2`
+pub fn signal_number(spec: &str) -> Option<c_int> {
+    if let Ok(number) = spec.parse::<c_int>() {
+        return signal_name(number).map(|_| number);
+    }
+    let upper = spec.to_uppercase();
+    let name = upper.strip_prefix("SIG").unwrap_or(&upper);
+    SIGNALS
+        .iter()
+        .find(|(known, _)| *known == name)
+        .map(|(_, number)| *number)
+}
+
+// Name without the `SIG` prefix
+pub fn signal_name(number: c_int) -> Option<&'static str> {
+    SIGNALS
+        .iter()
+        .find(|(_, known)| *known == number)
+        .map(|(name, _)| *name)
+}
+
+// Shared between the signal thread and the main thread: the signal thread only queues
+// trapped signals, the main thread runs their handlers between commands
+#[derive(Clone, Default)]
+pub struct SignalQueue {
+    trapped: Arc<Mutex<HashSet<c_int>>>,
+    pending: Arc<Mutex<Vec<c_int>>>,
+}
+
+impl SignalQueue {
+    pub fn set_trapped(&self, signal: c_int, trapped: bool) {
+        let mut set = self.trapped.lock().unwrap();
+        if trapped {
+            set.insert(signal);
+        } else {
+            set.remove(&signal);
+        }
+    }
+
+    pub fn is_trapped(&self, signal: c_int) -> bool {
+        self.trapped.lock().unwrap().contains(&signal)
+    }
+
+    pub fn push(&self, signal: c_int) {
+        self.pending.lock().unwrap().push(signal);
+    }
+
+    pub fn take(&self) -> Vec<c_int> {
+        std::mem::take(&mut *self.pending.lock().unwrap())
+    }
+}
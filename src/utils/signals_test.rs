@@ -0,0 +1,36 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+#[cfg(test)]
+mod tests {
+    use crate::utils::*;
+
+    #[test]
+    fn signal_number_test() {
+        assert_eq!(signal_number("INT"), Some(libc::SIGINT));
+        assert_eq!(signal_number("sigterm"), Some(libc::SIGTERM));
+        assert_eq!(signal_number("9"), Some(libc::SIGKILL));
+        assert_eq!(signal_number("NOPE"), None);
+        assert_eq!(signal_number("1000"), None);
+    }
+
+    #[test]
+    fn signal_name_test() {
+        assert_eq!(signal_name(libc::SIGHUP), Some("HUP"));
+        assert_eq!(signal_name(0), None);
+    }
+
+    #[test]
+    fn signal_queue_test() {
+        let queue = SignalQueue::default();
+        queue.set_trapped(libc::SIGUSR1, true);
+        assert!(queue.is_trapped(libc::SIGUSR1));
+        assert!(!queue.is_trapped(libc::SIGUSR2));
+
+        queue.push(libc::SIGUSR1);
+        assert_eq!(queue.take(), vec![libc::SIGUSR1]);
+        assert!(queue.take().is_empty());
+    }
+}
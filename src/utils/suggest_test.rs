@@ -0,0 +1,34 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+#[cfg(test)]
+mod tests {
+    use crate::utils::*;
+
+    fn strings(v: &[&str]) -> Vec<String> {
+        v.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn edit_distance_test() {
+        assert_eq!(edit_distance("git", "git"), 0);
+        assert_eq!(edit_distance("gti", "git"), 1);
+        assert_eq!(edit_distance("sl", "ls"), 1);
+        assert_eq!(edit_distance("pyhton3", "python3"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn closest_test() {
+        let candidates = strings(&["git", "gitk", "grep", "cat", "git"]);
+        assert_eq!(closest("gti", candidates.clone()), strings(&["git"]));
+        assert_eq!(
+            closest("gitt", candidates.clone()),
+            strings(&["git", "gitk"])
+        );
+        assert!(closest("zzzzzz", candidates).is_empty());
+    }
+}
@@ -0,0 +1,88 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use std::time::Duration;
+
+// Used by `time` when TIMEFORMAT is not set, the same as bash
+pub const DEFAULT_TIMEFORMAT: &str = "\nreal\t%3lR\nuser\t%3lU\nsys\t%3lS";
+// Used by `time -p`
+pub const POSIX_TIMEFORMAT: &str = "real %2R\nuser %2U\nsys %2S";
+
+// User and system CPU time, `who` is `RUSAGE_SELF` or `RUSAGE_CHILDREN`
+pub fn cpu_time(who: libc::c_int) -> (Duration, Duration) {
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    unsafe {
+        libc::getrusage(who, &mut usage);
+    }
+    (timeval(usage.ru_utime), timeval(usage.ru_stime))
+}
+
+pub fn timeval(tv: libc::timeval) -> Duration {
+    Duration::from_secs(tv.tv_sec as u64) + Duration::from_micros(tv.tv_usec as u64)
+}
+
+// `1.500` or, in the long form, `0m1.500s`
+pub fn format_duration(time: Duration, precision: usize, long: bool) -> String {
+    let secs = time.as_secs_f64();
+    if long {
+        let minutes = time.as_secs() / 60;
+        format!(
+            "{}m{:.*}s",
+            minutes,
+            precision,
+            secs - (minutes * 60) as f64
+        )
+    } else {
+        format!("{:.*}", precision, secs)
+    }
+}
+
+// Expands the escapes of TIMEFORMAT: `%R`, `%U` and `%S` for real, user and system time,
+// `%P` for the CPU percentage and `%%` for a percent sign
+// A digit after `%` sets the precision (3 at most) and `l` selects the long form
+pub fn format_time(format: &str, real: Duration, user: Duration, system: Duration) -> String {
+    let mut out = String::new();
+    let mut chars = format.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+
+        let mut spec = String::from("%");
+        let mut precision = 3;
+        let mut long = false;
+        if let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+            precision = (digit as usize).min(3);
+            spec.push(chars.next().unwrap());
+        }
+        if chars.peek() == Some(&'l') {
+            long = true;
+            spec.push(chars.next().unwrap());
+        }
+
+        match chars.next() {
+            Some('%') if spec == "%" => out.push('%'),
+            Some('R') => out.push_str(&format_duration(real, precision, long)),
+            Some('U') => out.push_str(&format_duration(user, precision, long)),
+            Some('S') => out.push_str(&format_duration(system, precision, long)),
+            Some('P') => {
+                let cpu = (user + system).as_secs_f64();
+                let real = real.as_secs_f64();
+                let percent = if real > 0.0 { cpu / real * 100.0 } else { 0.0 };
+                out.push_str(&format!("{:.2}", percent));
+            }
+            // Anything else is printed the way it was written
+            Some(other) => {
+                out.push_str(&spec);
+                out.push(other);
+            }
+            None => out.push_str(&spec),
+        }
+    }
+    out
+}
@@ -0,0 +1,40 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::utils::*;
+
+    #[test]
+    fn format_duration_test() {
+        let time = Duration::from_millis(61_500);
+        assert_eq!(format_duration(time, 3, false), "61.500");
+        assert_eq!(format_duration(time, 3, true), "1m1.500s");
+        assert_eq!(format_duration(time, 0, true), "1m2s");
+    }
+
+    #[test]
+    fn format_time_test() {
+        let real = Duration::from_secs(2);
+        let user = Duration::from_millis(500);
+        let system = Duration::from_millis(250);
+
+        assert_eq!(
+            format_time(DEFAULT_TIMEFORMAT, real, user, system),
+            "\nreal\t0m2.000s\nuser\t0m0.500s\nsys\t0m0.250s"
+        );
+        assert_eq!(
+            format_time(POSIX_TIMEFORMAT, real, user, system),
+            "real 2.00\nuser 0.50\nsys 0.25"
+        );
+        assert_eq!(
+            format_time("%P%% of %1R", real, user, system),
+            "37.50% of 2.0"
+        );
+        assert_eq!(format_time("%x %", real, user, system), "%x %");
+    }
+}
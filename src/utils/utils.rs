@@ -4,14 +4,17 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/.
  */
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
+use std::ffi::{CStr, OsStr};
 use std::fs::{self, File};
-use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::io::{self, Read, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
 
 use lazy_static::lazy_static;
-use serde_derive::Deserialize;
+use serde_derive::{Deserialize, Serialize};
 
 const BASE_PROMPT: &str = "`$ `";
 
@@ -34,26 +37,53 @@ truecolors = false
 true_suggestion_color = [255, 0, 0]
 "#;
 
-// Config for .vshrc.toml config file
+// Config for the config.toml config file
 // For example: prompt would mean [prompt]
-#[derive(Deserialize, Clone)]
+#[derive(Deserialize, Serialize, Clone, Default)]
 pub struct Config {
     pub prompt: Option<PromptConfig>,
     pub misc: Option<Misc>,
     pub effects: Option<EffectsCtx>,
+    // `set -o` and `shopt` options by name, e.g. `pipefail = true`
+    pub options: Option<HashMap<String, bool>>,
+    pub command_not_found: Option<CommandNotFound>,
+    // Abbreviations expanded while typing, e.g. `gco = "git checkout"`
+    pub abbr: Option<HashMap<String, String>>,
+    pub env: Option<EnvConfig>,
 }
 
-#[derive(Deserialize, Clone)]
+// Environment variables set for every session, for [env]
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct EnvConfig {
+    // Directories put before and after the ones already in $PATH
+    pub path_prepend: Option<Vec<String>>,
+    pub path_append: Option<Vec<String>>,
+    #[serde(flatten)]
+    pub vars: BTreeMap<String, String>,
+}
+
+// What happens when a command cannot be found, for [command_not_found]
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct CommandNotFound {
+    // Suggest similar commands, on by default
+    pub suggest: Option<bool>,
+    // Call the `command_not_found_handler` function when it is defined, on by default
+    pub use_function: Option<bool>,
+    // Command run with the missing command and its arguments appended
+    pub handler: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
 pub struct Misc {
     pub alias: Option<Vec<[String; 2]>>,
 }
 
-#[derive(Deserialize, Clone)]
+#[derive(Deserialize, Serialize, Clone)]
 pub struct PromptConfig {
     pub theme: Option<String>,
 }
 
-#[derive(Deserialize, Clone)]
+#[derive(Deserialize, Serialize, Clone)]
 pub struct EffectsCtx {
     pub truecolors: Option<bool>,
     pub underlined: Option<bool>,
@@ -63,14 +93,80 @@ pub struct EffectsCtx {
     pub true_suggestion_color: Option<[u8; 3]>,
 }
 
-pub fn fetch_data() -> String {
-    let mut path = PathBuf::from(env::var("HOME").unwrap());
-    path.push(".config");
-    path.push("vsh");
-    path.push("config.toml");
+// $HOME, or the home directory of the user's passwd entry when HOME is unset or empty
+pub fn home_dir() -> Option<PathBuf> {
+    if let Some(home) = env::var_os("HOME").filter(|home| !home.is_empty()) {
+        return Some(PathBuf::from(home));
+    }
+    let dir = unsafe {
+        let passwd = libc::getpwuid(libc::getuid());
+        if passwd.is_null() || (*passwd).pw_dir.is_null() {
+            return None;
+        }
+        CStr::from_ptr((*passwd).pw_dir)
+    };
+    Some(PathBuf::from(OsStr::from_bytes(dir.to_bytes())))
+}
+
+// An XDG base directory: the variable's value if it is an absolute path, as the spec asks,
+// otherwise `fallback` inside the home directory
+pub fn base_dir(xdg: Option<&Path>, home: Option<&Path>, fallback: &str) -> PathBuf {
+    match (xdg, home) {
+        (Some(dir), _) if dir.is_absolute() => dir.to_path_buf(),
+        (_, Some(home)) => home.join(fallback),
+        // Without any home, the root is a better guess than the current directory
+        (_, None) => Path::new("/").join(fallback),
+    }
+}
+
+fn xdg_dir(var: &str, fallback: &str) -> PathBuf {
+    let xdg = env::var_os(var).map(PathBuf::from);
+    base_dir(xdg.as_deref(), home_dir().as_deref(), fallback)
+}
+
+// $XDG_CONFIG_HOME/vsh, where config.toml and the init, profile and logout scripts live
+pub fn config_dir() -> PathBuf {
+    xdg_dir("XDG_CONFIG_HOME", ".config").join("vsh")
+}
+
+// $XDG_STATE_HOME/vsh/history, unless there is a ~/.vsh_history from older versions
+pub fn history_path() -> PathBuf {
+    if let Some(legacy) = home_dir().map(|home| home.join(".vsh_history")) {
+        if legacy.exists() {
+            return legacy;
+        }
+    }
+    xdg_dir("XDG_STATE_HOME", ".local/state")
+        .join("vsh")
+        .join("history")
+}
+
+pub fn config_path() -> PathBuf {
+    config_dir().join("config.toml")
+}
+
+// Where the config file was before it moved to the config directory
+pub fn legacy_config_path() -> Option<PathBuf> {
+    home_dir().map(|home| home.join(".vshrc.toml"))
+}
+
+// Moves the legacy config file to `to`, which must not exist yet
+pub fn migrate_config(from: &Path, to: &Path) -> io::Result<()> {
+    if let Some(dir) = to.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    // A rename cannot cross file systems, a copy can
+    fs::rename(from, to).or_else(|_| fs::copy(from, to).and_then(|_| fs::remove_file(from)))
+}
+
+// Reads the config file, the default one is created when it is missing
+pub fn fetch_data(path: &Path) -> String {
+    let mut path = path.to_path_buf();
 
     let mut data = String::new();
-    if path.exists() {
+    if path != config_path() && !path.exists() {
+        eprintln!("vsh: {}: config file not found", path.display());
+    } else if path.exists() {
         match File::open(&path) {
             Ok(mut x) => {
                 if x.read_to_string(&mut data).is_err() {
@@ -95,7 +191,7 @@ pub fn fetch_data() -> String {
                 if x.write_all(BASE_CONFIG.as_bytes()).is_err() {
                     eprintln!("vsh: Could not write to config file")
                 }
-                data = String::from("");
+                data = String::from(BASE_CONFIG);
             }
             Err(_) => eprintln!("vsh: Config File could not be created!"),
         }
@@ -103,19 +199,13 @@ pub fn fetch_data() -> String {
     data
 }
 
-pub fn get_toml(data: String) -> Result<Config, String> {
-    match toml::from_str::<Config>(&data) {
-        Ok(ok) => Ok(ok),
-        Err(e) => Err(e.to_string()),
-    }
-}
-
-pub fn get_alias(data: &Config) -> HashMap<&str, &str> {
-    let mut list: HashMap<&str, &str> = HashMap::new();
+pub fn get_alias(data: &Config) -> HashMap<String, String> {
+    let mut list: HashMap<String, String> = HashMap::new();
     if let Some(misc) = &data.misc {
         if let Some(alias) = &misc.alias {
-            for x in alias.iter() {
-                list.insert(&x[0], &x[1]);
+            // The default config comes with an empty `["", ""]` entry
+            for x in alias.iter().filter(|x| !x[0].is_empty()) {
+                list.insert(x[0].to_string(), x[1].to_string());
             }
         }
     }
@@ -124,13 +214,11 @@ pub fn get_alias(data: &Config) -> HashMap<&str, &str> {
 
 pub fn get_theme(data: &Config) -> String {
     match &data.prompt {
-        Some(prompt) => {
-            match &prompt.theme {
-                Some(x) => x.to_string(),
-                None => String::from(BASE_PROMPT)
-            }
-        }
-        None => String::from(BASE_PROMPT)
+        Some(prompt) => match &prompt.theme {
+            Some(x) => x.to_string(),
+            None => String::from(BASE_PROMPT),
+        },
+        None => String::from(BASE_PROMPT),
     }
 }
 
@@ -138,5 +226,62 @@ pub fn expand(raw: String) -> String {
     lazy_static! {
         static ref RE: fancy_regex::Regex = fancy_regex::Regex::new("(?<!\\\\)\\~").unwrap();
     }
-    RE.replace_all(&raw, env::var("HOME").unwrap()).to_string()
+    match home_dir() {
+        Some(home) => RE
+            .replace_all(&raw, home.to_string_lossy().as_ref())
+            .to_string(),
+        None => raw,
+    }
+}
+
+// Text of an io::Error without the ` (os error 2)` suffix
+pub fn os_error_text(error: &io::Error) -> String {
+    let text = error.to_string();
+    match text.find(" (os error ") {
+        Some(end) => text[..end].to_string(),
+        None => text,
+    }
+}
+
+fn is_executable(path: &Path) -> bool {
+    match fs::metadata(path) {
+        Ok(meta) => meta.is_file() && meta.permissions().mode() & 0o111 != 0,
+        Err(_) => false,
+    }
+}
+
+// Every executable called `name` in $PATH, in lookup order
+// Names containing a slash are taken as paths and not looked up
+pub fn find_in_path(name: &str) -> Vec<PathBuf> {
+    if name.is_empty() {
+        return Vec::new();
+    }
+    if name.contains('/') {
+        let path = PathBuf::from(name);
+        return if is_executable(&path) {
+            vec![path]
+        } else {
+            Vec::new()
+        };
+    }
+
+    let path_var = env::var("PATH").unwrap_or_default();
+    env::split_paths(&path_var)
+        .map(|dir| dir.join(name))
+        .filter(|path| is_executable(path))
+        .collect()
+}
+
+// Names of every executable in $PATH, sorted and without duplicates
+pub fn path_executables() -> Vec<String> {
+    let path_var = env::var("PATH").unwrap_or_default();
+    let mut names = env::split_paths(&path_var)
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.flatten())
+        .filter(|entry| is_executable(&entry.path()))
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect::<Vec<_>>();
+    names.sort();
+    names.dedup();
+    names
 }
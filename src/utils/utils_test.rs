@@ -0,0 +1,45 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use crate::utils::*;
+
+    #[test]
+    fn base_dir_test() {
+        let home = Some(Path::new("/home/me"));
+        assert_eq!(
+            base_dir(Some(Path::new("/xdg")), home, ".config"),
+            PathBuf::from("/xdg")
+        );
+        // Relative XDG paths are invalid and ignored
+        assert_eq!(
+            base_dir(Some(Path::new("xdg")), home, ".config"),
+            PathBuf::from("/home/me/.config")
+        );
+        assert_eq!(
+            base_dir(None, home, ".local/state"),
+            PathBuf::from("/home/me/.local/state")
+        );
+        assert_eq!(base_dir(None, None, ".config"), PathBuf::from("/.config"));
+    }
+
+    #[test]
+    fn migrate_config_test() {
+        let dir = std::env::temp_dir().join(format!("vsh-migrate-{}", std::process::id()));
+        let legacy = dir.join(".vshrc.toml");
+        let new = dir.join(".config/vsh/config.toml");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(&legacy, "[prompt]\n").unwrap();
+
+        migrate_config(&legacy, &new).unwrap();
+        assert!(!legacy.exists());
+        assert_eq!(std::fs::read_to_string(&new).unwrap(), "[prompt]\n");
+        assert!(migrate_config(&legacy, &new).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,324 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+
+use toml::value::Table;
+use toml::Value;
+
+use crate::options::{SET_OPTIONS, SHOPT_OPTIONS};
+use crate::theme::parser::{parse_theme, Span};
+use crate::utils::{closest, valid_env_name, Config};
+
+// Every section of the config with the keys it takes, `None` takes any key
+const SECTIONS: &[(&str, Option<&[&str]>)] = &[
+    ("abbr", None),
+    (
+        "command_not_found",
+        Some(&["suggest", "use_function", "handler"]),
+    ),
+    (
+        "effects",
+        Some(&[
+            "truecolors",
+            "underlined",
+            "bold",
+            "dimmed",
+            "suggestion_color",
+            "true_suggestion_color",
+        ]),
+    ),
+    ("env", None),
+    ("misc", Some(&["alias"])),
+    ("options", None),
+    ("profile", None),
+    ("prompt", Some(&["theme"])),
+];
+
+// What a `match` rule of a profile can test
+pub const PROFILE_MATCH: &[&str] = &["hostname", "ssh"];
+
+// Something wrong in a config file, with the 1-based line and column it is at when known
+#[derive(Debug, PartialEq)]
+pub struct Problem {
+    pub error: bool,
+    pub position: Option<(usize, usize)>,
+    pub message: String,
+}
+
+impl Problem {
+    pub fn warning(position: Option<(usize, usize)>, message: String) -> Self {
+        Self {
+            error: false,
+            position,
+            message,
+        }
+    }
+
+    // `path:line:column: error: message`, the way compilers point at a spot
+    pub fn describe(&self, path: &Path) -> String {
+        let position = match self.position {
+            Some((line, column)) => format!(":{}:{}", line, column),
+            None => String::new(),
+        };
+        let kind = if self.error { "error" } else { "warning" };
+        format!("{}{}: {}: {}", path.display(), position, kind, self.message)
+    }
+}
+
+pub fn did_you_mean(name: &str, candidates: &[&str]) -> String {
+    let found = closest(name, candidates.iter().map(|c| c.to_string()));
+    if found.is_empty() {
+        return String::new();
+    }
+    let found = found
+        .iter()
+        .map(|name| format!("`{}`", name))
+        .collect::<Vec<_>>();
+    format!(", did you mean {}?", found.join(", "))
+}
+
+// Where `[section]`, or `key` inside it, is written, as a 1-based line and column
+pub fn locate(text: &str, section: Option<&str>, key: &str) -> Option<(usize, usize)> {
+    let mut current = None;
+    for (number, line) in text.lines().enumerate() {
+        let trimmed = line.trim_start();
+        let column = line.len() - trimmed.len() + 1;
+        if let Some(header) = trimmed.strip_prefix('[') {
+            let name = header.split(']').next().unwrap_or_default().trim();
+            if section.is_none() && name == key {
+                return Some((number + 1, column));
+            }
+            current = Some(name.to_string());
+            continue;
+        }
+        // A quoted key can hold `=` itself
+        let name = match trimmed.chars().next() {
+            Some(quote @ ('"' | '\'')) => trimmed[1..].split(quote).next(),
+            Some(_) => trimmed.split('=').next().map(str::trim),
+            None => None,
+        };
+        if current.as_deref() == section && name == Some(key) {
+            return Some((number + 1, column));
+        }
+    }
+    None
+}
+
+// Warns about the keys of a `[section]` that are not in `known`
+fn check_keys(
+    text: &str,
+    entries: &Value,
+    section: &str,
+    known: &[&str],
+    problems: &mut Vec<Problem>,
+) {
+    for key in entries
+        .as_table()
+        .into_iter()
+        .flat_map(|table| table.keys())
+    {
+        if !known.contains(&key.as_str()) {
+            problems.push(Problem::warning(
+                locate(text, Some(section), key),
+                format!(
+                    "unknown key `{}` in [{}]{}",
+                    key,
+                    section,
+                    did_you_mean(key, known)
+                ),
+            ));
+        }
+    }
+}
+
+// Checks the sections of `table`, which is the whole config when `prefix` is empty and a
+// profile like `profile.work` otherwise
+fn check_sections(text: &str, table: &Table, prefix: &str, problems: &mut Vec<Problem>) {
+    let names = SECTIONS.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+    for (name, entries) in table {
+        let section = if prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}.{}", prefix, name)
+        };
+        match (prefix.is_empty(), name.as_str()) {
+            // `include = ["file.toml", ...]` is the one key outside of a section
+            (true, "include") => {
+                let paths = entries
+                    .as_array()
+                    .filter(|list| list.iter().all(Value::is_str));
+                if paths.is_none() {
+                    problems.push(Problem {
+                        error: true,
+                        position: locate(text, None, name),
+                        message: "`include` must be a list of paths".to_string(),
+                    });
+                }
+                continue;
+            }
+            (true, "profile") | (false, "match") => continue,
+            // The environment cannot hold a name with `=` or a NUL byte anywhere
+            (_, "env") => {
+                for (key, value) in entries.as_table().into_iter().flatten() {
+                    let message = if !valid_env_name(key) {
+                        "is not a valid environment variable name"
+                    } else if value.as_str().is_some_and(|value| value.contains('\0')) {
+                        "cannot hold a NUL byte"
+                    } else {
+                        continue;
+                    };
+                    problems.push(Problem {
+                        error: true,
+                        position: locate(text, Some(&section), key),
+                        message: format!("`{}` in [{}] {}", key, section, message),
+                    });
+                }
+            }
+            _ => (),
+        }
+        let keys = match SECTIONS.iter().find(|(known, _)| known == name) {
+            Some((_, keys)) => keys,
+            None => {
+                problems.push(Problem::warning(
+                    locate(text, None, &section),
+                    format!(
+                        "unknown section `{}`{}",
+                        section,
+                        did_you_mean(name, &names)
+                    ),
+                ));
+                continue;
+            }
+        };
+
+        let known = match (name.as_str(), keys) {
+            ("options", _) => SET_OPTIONS
+                .iter()
+                .map(|(option, _)| *option)
+                .chain(SHOPT_OPTIONS.iter().copied())
+                .collect(),
+            (_, Some(keys)) => keys.to_vec(),
+            (_, None) => continue,
+        };
+        check_keys(text, entries, &section, &known, problems);
+    }
+}
+
+// Checks a config file's text: a syntax or type error is an error, unknown sections, keys
+// and options are warnings
+pub fn validate_config(text: &str) -> Vec<Problem> {
+    let value = match toml::from_str::<Value>(text) {
+        Ok(value) => value,
+        Err(e) => return vec![toml_problem(&e)],
+    };
+    let mut problems = Vec::new();
+    if let Err(e) = toml::from_str::<Config>(text) {
+        problems.push(toml_problem(&e));
+    }
+
+    let table = value.as_table().unwrap();
+    check_sections(text, table, "", &mut problems);
+    // `[profile.<name>]` takes the same sections as the config, and the rule choosing it
+    if let Some(profiles) = table.get("profile").and_then(Value::as_table) {
+        for (name, profile) in profiles {
+            let prefix = format!("profile.{}", name);
+            if let Some(profile) = profile.as_table() {
+                check_sections(text, profile, &prefix, &mut problems);
+            }
+            if let Some(rule) = profile.get("match") {
+                let section = format!("{}.match", prefix);
+                check_keys(text, rule, &section, PROFILE_MATCH, &mut problems);
+            }
+        }
+    }
+
+    problems.sort_by_key(|problem| problem.position);
+    problems
+}
+
+fn toml_problem(error: &toml::de::Error) -> Problem {
+    // The message ends in ` at line 3 column 7`, which the position already says
+    let message = error.to_string();
+    let message = match message.rfind(" at line ") {
+        Some(end) if error.line_col().is_some() => message[..end].to_string(),
+        _ => message,
+    };
+    Problem {
+        error: true,
+        position: error
+            .line_col()
+            .map(|(line, column)| (line + 1, column + 1)),
+        message,
+    }
+}
+
+// Checks the theme that is in use, `from_config` tells whether it came from the config file
+pub fn check_theme(text: &str, theme: &str, from_config: bool) -> Option<Problem> {
+    let message = format!("theme: {}", validate_theme(theme).err()?);
+    let position = if from_config {
+        locate(text, Some("prompt"), "theme")
+    } else {
+        None
+    };
+    Some(Problem {
+        error: true,
+        position,
+        message,
+    })
+}
+
+// Parses a prompt theme, the error says where it stopped making sense
+pub fn validate_theme(theme: &str) -> Result<(), String> {
+    match parse_theme(Span::new(theme)) {
+        Ok(_) => Ok(()),
+        Err(nom::Err::Error(e) | nom::Err::Failure(e)) => Err(format!(
+            "parse error at column {} near `{}`",
+            e.input.get_column(),
+            e.input.fragment()
+        )),
+        Err(nom::Err::Incomplete(_)) => Err("unexpected end of theme".to_string()),
+    }
+}
+
+// Why a file with this mode and owner is unsafe to load for user `uid`, anyone who can change
+// it can run commands as that user; files of root are trusted
+pub fn permission_problem(mode: u32, owner: u32, uid: u32) -> Option<String> {
+    if owner != uid && owner != 0 {
+        Some(format!(
+            "it is owned by another user (uid {}), who could use it to run commands as you (fix with `chown`)",
+            owner
+        ))
+    } else if mode & 0o002 != 0 {
+        Some(
+            "it is writable by every user, who could use it to run commands as you (fix with `chmod o-w`)"
+                .to_string(),
+        )
+    } else {
+        None
+    }
+}
+
+// The permission problem of a config or startup file, if it has one; it is left out unless
+// `allow`, which --allow-insecure-config sets
+pub fn check_permissions(path: &Path, allow: bool) -> Option<Problem> {
+    let meta = fs::metadata(path).ok()?;
+    let reason = permission_problem(meta.mode(), meta.uid(), unsafe { libc::getuid() })?;
+    let message = if allow {
+        format!(
+            "{}; loaded anyway because of --allow-insecure-config",
+            reason
+        )
+    } else {
+        format!(
+            "not loaded: {}; use --allow-insecure-config to load it anyway",
+            reason
+        )
+    };
+    Some(Problem::warning(None, message))
+}
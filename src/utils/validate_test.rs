@@ -0,0 +1,114 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::utils::*;
+
+    #[test]
+    fn validate_config_test() {
+        assert_eq!(validate_config("[prompt]\ntheme = \"`$ `\"\n"), vec![]);
+
+        let problems = validate_config("[effects]\nbold = true\nunderlind = false\n\n[efects]\n");
+        assert_eq!(problems.len(), 2);
+        assert_eq!(problems[0].position, Some((3, 1)));
+        assert_eq!(
+            problems[0].message,
+            "unknown key `underlind` in [effects], did you mean `underlined`?"
+        );
+        assert!(!problems[0].error);
+        assert_eq!(problems[1].position, Some((5, 1)));
+        assert!(problems[1].message.contains("did you mean `effects`?"));
+
+        let problems = validate_config("[options]\n  pipefal = true\n");
+        assert_eq!(problems[0].position, Some((2, 3)));
+        assert!(problems[0].message.contains("`pipefail`"));
+
+        let problems = validate_config("[effects]\nbold = \"yes\"\n");
+        assert!(problems[0].error);
+        assert_eq!(problems[0].position, Some((2, 8)));
+
+        let problems = validate_config(
+            "[profile.work.prompt]\nthem = 1\n[profile.work.match]\nhostnme = 'x'\n[profile.ssh.efects]\n",
+        );
+        let messages = problems
+            .iter()
+            .map(|p| p.message.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            vec![
+                "unknown key `them` in [profile.work.prompt], did you mean `theme`?",
+                "unknown key `hostnme` in [profile.work.match], did you mean `hostname`?",
+                "unknown section `profile.ssh.efects`, did you mean `effects`?",
+            ]
+        );
+        assert_eq!(problems[0].position, Some((2, 1)));
+
+        let problems = validate_config("[env]\nOK = 'x'\n\"A=B\" = 'x'\nNUL = \"\\u0000\"\n");
+        let found = problems
+            .iter()
+            .map(|p| (p.error, p.position, p.message.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    true,
+                    Some((3, 1)),
+                    "`A=B` in [env] is not a valid environment variable name"
+                ),
+                (true, Some((4, 1)), "`NUL` in [env] cannot hold a NUL byte"),
+            ]
+        );
+
+        let problems = validate_config("[prompt\n");
+        assert!(problems[0].error);
+        assert_eq!(problems[0].position.map(|(line, _)| line), Some(1));
+    }
+
+    #[test]
+    fn problem_describe_test() {
+        let problem = Problem {
+            error: true,
+            position: Some((2, 8)),
+            message: "bad".to_string(),
+        };
+        assert_eq!(
+            problem.describe(Path::new("config.toml")),
+            "config.toml:2:8: error: bad"
+        );
+    }
+
+    #[test]
+    fn validate_theme_test() {
+        assert!(validate_theme("&[#7393B3]`[`{{current_dir}}`] `").is_ok());
+        assert!(validate_theme("`unclosed").is_err());
+
+        let text = "[prompt]\ntheme = \"`unclosed\"\n";
+        let problem = check_theme(text, "`unclosed", true).unwrap();
+        assert_eq!(problem.position, Some((2, 1)));
+        assert_eq!(
+            check_theme(text, "`unclosed", false).unwrap().position,
+            None
+        );
+        assert_eq!(check_theme(text, "`$ `", false), None);
+    }
+
+    #[test]
+    fn permission_problem_test() {
+        assert_eq!(permission_problem(0o100644, 1000, 1000), None);
+        // Root's files are trusted, like /etc ones
+        assert_eq!(permission_problem(0o100644, 0, 1000), None);
+        assert!(permission_problem(0o100666, 1000, 1000)
+            .unwrap()
+            .contains("writable by every user"));
+        assert!(permission_problem(0o100600, 1001, 1000)
+            .unwrap()
+            .contains("owned by another user (uid 1001)"));
+    }
+}